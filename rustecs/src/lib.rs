@@ -1,4 +1,32 @@
-use std::collections::HashMap;
+extern crate time;
+
+
+use std::collections::{
+	hash_set,
+	HashMap,
+	HashSet,
+	RingBuf,
+};
+use std::slice;
+
+#[cfg(feature = "parallel")] use std::cell::Cell;
+#[cfg(feature = "parallel")] use std::kinds::marker;
+#[cfg(feature = "parallel")] use std::mem;
+#[cfg(feature = "parallel")] use std::sync::{
+	Arc,
+	Mutex,
+};
+#[cfg(feature = "parallel")] use std::sync::mpsc::{
+	channel,
+	Receiver,
+	Sender,
+};
+#[cfg(feature = "parallel")] use std::thread::Thread;
+
+#[cfg(feature = "profiling")] use std::cmp;
+#[cfg(feature = "profiling")] use std::collections::hash_map;
+
+#[cfg(feature = "profiling")] pub use time::precise_time_ns;
 
 
 pub type EntityId = u32;
@@ -10,28 +38,107 @@ pub fn components<T>() -> Components<T> {
 	HashMap::new()
 }
 
+pub fn components_with_capacity<T>(capacity: uint) -> Components<T> {
+	HashMap::with_capacity(capacity)
+}
+
+
+/// Iterates over the ids of all entities in a container.
+pub struct Ids<'a> {
+	ids: hash_set::Iter<'a, EntityId>,
+}
+
+impl<'a> Ids<'a> {
+	pub fn new(ids: &'a HashSet<EntityId>) -> Ids<'a> {
+		Ids {
+			ids: ids.iter(),
+		}
+	}
+}
+
+impl<'a> Iterator<EntityId> for Ids<'a> {
+	fn next(&mut self) -> Option<EntityId> {
+		self.ids.next().map(|id| *id)
+	}
+
+	fn size_hint(&self) -> (uint, Option<uint>) {
+		self.ids.size_hint()
+	}
+}
+
+
+/// Decides what happens, if an entity is imported with an id that is already
+/// in use. Unless a world declares otherwise, `Replace` is used, so the
+/// imported entity doesn't end up with components it doesn't have.
+#[deriving(Clone, PartialEq, Show)]
+pub enum ImportPolicy {
+	/// Remove all components of the existing entity before inserting the
+	/// imported ones.
+	Replace,
+	/// Overwrite the components that the imported entity has, keep all others.
+	Merge,
+	/// Keep the existing entity and ignore the imported one.
+	Reject,
+}
+
 
 pub trait EntityContainer<E> {
 	fn add(&mut self, entity: E) -> EntityId;
-	fn import(&mut self, id: EntityId, entity: E);
+
+	/// Imports an entity using the world's default import policy. Returns
+	/// `false`, if the entity was rejected.
+	fn import(&mut self, id: EntityId, entity: E) -> bool;
+
+	/// Imports an entity using the given import policy. Returns `false`, if
+	/// the entity was rejected.
+	fn import_with(&mut self, id: EntityId, entity: E, policy: ImportPolicy)
+		-> bool;
+
 	fn remove(&mut self, id: EntityId);
 
+	/// Copies all components of the entity `id` to a new entity with the id
+	/// `new_id`. Returns `false`, if there's no entity `id`.
+	///
+	/// Only worlds that derive `Clone` can duplicate entities. All others
+	/// always return `false`.
+	fn duplicate_as(&mut self, _id: EntityId, _new_id: EntityId) -> bool {
+		false
+	}
+
+	fn add_batch<I: Iterator<E>>(&mut self, mut entities: I) -> Vec<EntityId> {
+		let mut ids = Vec::new();
+		for entity in entities {
+			ids.push(self.add(entity));
+		}
+
+		ids
+	}
+
+	fn remove_batch<I: Iterator<EntityId>>(&mut self, mut ids: I) {
+		for id in ids {
+			self.remove(id);
+		}
+	}
+
 	fn export(self) -> Vec<(EntityId, E)>;
 }
 
 
+#[deriving(Clone, PartialEq, Show)]
 pub struct Control<E> {
-	next_id : EntityId,
-	imported: Vec<(EntityId, E)>,
-	removed : Vec<EntityId>,
+	next_id   : EntityId,
+	imported  : Vec<(EntityId, E, Option<ImportPolicy>)>,
+	duplicated: Vec<(EntityId, EntityId)>,
+	removed   : Vec<EntityId>,
 }
 
 impl<E> Control<E> {
 	pub fn new() -> Control<E> {
 		Control {
-			next_id : 1, // generate odd ids to avoid collisions
-			imported: Vec::new(),
-			removed : Vec::new(),
+			next_id   : 1, // generate odd ids to avoid collisions
+			imported  : Vec::new(),
+			duplicated: Vec::new(),
+			removed   : Vec::new(),
 		}
 	}
 
@@ -39,32 +146,638 @@ impl<E> Control<E> {
 		let id = self.next_id;
 		self.next_id += 2;
 
-		self.imported.push((id, entity));
+		self.imported.push((id, entity, None));
 		id
 	}
 
+	pub fn add_batch<I: Iterator<E>>(&mut self, mut entities: I) -> Vec<EntityId> {
+		let (lower, _) = entities.size_hint();
+		self.imported.reserve(lower);
+
+		let mut ids = Vec::with_capacity(lower);
+		for entity in entities {
+			ids.push(self.add(entity));
+		}
+
+		ids
+	}
+
 	pub fn import(&mut self, id: EntityId, entity: E) {
-		self.imported.push((id, entity));
+		self.imported.push((id, entity, None));
+	}
+
+	pub fn import_with(&mut self, id: EntityId, entity: E, policy: ImportPolicy) {
+		self.imported.push((id, entity, Some(policy)));
 	}
 
 	pub fn remove(&mut self, id: EntityId) {
 		self.removed.push(id);
 	}
 
+	pub fn remove_batch<I: Iterator<EntityId>>(&mut self, ids: I) {
+		self.removed.extend(ids);
+	}
+
 	pub fn apply<Es: EntityContainer<E>>(&mut self, entities: &mut Es) {
 		loop {
 			match self.imported.pop() {
-				Some((id, entity)) =>
-					entities.import(id, entity),
+				Some((id, entity, None)) => {
+					entities.import(id, entity);
+				},
+				Some((id, entity, Some(policy))) => {
+					entities.import_with(id, entity, policy);
+				},
 				None =>
 					break,
 			}
 		}
+		for &(id, new_id) in self.duplicated.iter() {
+			entities.duplicate_as(id, new_id);
+		}
 		for &id in self.removed.iter() {
 			entities.remove(id);
 		}
 
 		self.imported.clear();
+		self.duplicated.clear();
 		self.removed.clear();
 	}
 }
+
+impl<E: Clone> Control<E> {
+	/// Duplicates the entity `id` when the control is applied. Returns the id
+	/// of the copy.
+	///
+	/// Only entities that implement `Clone` can be duplicated, which is the
+	/// case if the world derives it.
+	pub fn duplicate(&mut self, id: EntityId) -> EntityId {
+		let new_id = self.next_id;
+		self.next_id += 2;
+
+		self.duplicated.push((id, new_id));
+		new_id
+	}
+}
+
+impl<E> Extend<E> for Control<E> {
+	fn extend<I: Iterator<E>>(&mut self, entities: I) {
+		self.add_batch(entities);
+	}
+}
+
+
+/// Decides when the control owned by the generated `Systems` is applied.
+#[deriving(Clone, PartialEq, Show)]
+pub enum ApplyControl {
+	/// Apply the control after each system call.
+	AfterSystem,
+	/// Apply the control after all systems for an event have been called.
+	AfterTrigger,
+	/// Only apply the control when explicitly asked to.
+	Manual,
+}
+
+
+/// Events that are waiting to be dispatched by the generated `Systems`.
+#[deriving(Clone, PartialEq, Show)]
+pub struct EventQueue<E> {
+	events: RingBuf<E>,
+}
+
+impl<E> EventQueue<E> {
+	pub fn new() -> EventQueue<E> {
+		EventQueue {
+			events: RingBuf::new(),
+		}
+	}
+
+	pub fn push(&mut self, event: E) {
+		self.events.push_back(event);
+	}
+
+	pub fn pop(&mut self) -> Option<E> {
+		self.events.pop_front()
+	}
+
+	pub fn len(&self) -> uint {
+		self.events.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.events.is_empty()
+	}
+
+	pub fn clear(&mut self) {
+		self.events.clear();
+	}
+}
+
+
+/// How many queued events `Systems` dispatches at most, before giving up. This
+/// protects against systems that keep queueing events in response to each
+/// other.
+pub const DEFAULT_DISPATCH_LIMIT: uint = 1024;
+
+/// Returned, if dispatching queued events has been stopped, because the
+/// dispatch limit was reached. The remaining events stay in the queue.
+#[deriving(Clone, PartialEq, Show)]
+pub struct DispatchLimitExceeded {
+	pub dispatched: uint,
+	pub remaining : uint,
+}
+
+
+/// Identifies a system that was registered at runtime.
+#[deriving(Clone, PartialEq, Eq, Hash, Show)]
+pub struct SystemHandle(u32);
+
+/// Holds systems that are registered at runtime, together with the kind of
+/// event they're listening to.
+pub struct Registry<K, S> {
+	next_handle: u32,
+	systems    : Vec<(SystemHandle, K, S)>,
+}
+
+impl<K, S> Registry<K, S> {
+	pub fn new() -> Registry<K, S> {
+		Registry {
+			next_handle: 0,
+			systems    : Vec::new(),
+		}
+	}
+
+	pub fn register(&mut self, kind: K, system: S) -> SystemHandle {
+		let handle = SystemHandle(self.next_handle);
+		self.next_handle += 1;
+
+		self.systems.push((handle.clone(), kind, system));
+
+		handle
+	}
+
+	/// Returns whether a system with the given handle was registered.
+	pub fn unregister(&mut self, handle: SystemHandle) -> bool {
+		match self.systems.iter().position(|&(ref h, _, _)| *h == handle) {
+			Some(index) => {
+				self.systems.remove(index);
+				true
+			},
+			None =>
+				false,
+		}
+	}
+
+	pub fn len(&self) -> uint {
+		self.systems.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.systems.is_empty()
+	}
+
+	/// Iterates over the registered systems, in the order of registration.
+	pub fn iter_mut(&mut self) -> slice::IterMut<(SystemHandle, K, S)> {
+		self.systems.iter_mut()
+	}
+}
+
+
+/// The threads that run non-conflicting systems at the same time. They are
+/// started once and kept around, so triggering an event doesn't start any new
+/// threads. Only available with the parallel feature.
+#[cfg(feature = "parallel")]
+pub struct ThreadPool {
+	jobs   : Sender<Message>,
+	done   : Receiver<bool>,
+	threads: uint,
+}
+
+#[cfg(feature = "parallel")]
+impl ThreadPool {
+	pub fn new(threads: uint) -> ThreadPool {
+		assert!(threads > 0, "A thread pool needs at least one thread");
+
+		let (jobs_sender, jobs_receiver) = channel();
+		let (done_sender, done_receiver) = channel();
+
+		let jobs = Arc::new(Mutex::new(jobs_receiver));
+		for _ in range(0, threads) {
+			spawn_worker(jobs.clone(), done_sender.clone());
+		}
+
+		ThreadPool {
+			jobs   : jobs_sender,
+			done   : done_receiver,
+			threads: threads,
+		}
+	}
+
+	pub fn threads(&self) -> uint {
+		self.threads
+	}
+
+	/// Calls the closure with a scope that jobs can be passed to. Jobs may
+	/// borrow anything that outlives the scope, as all of them have finished
+	/// before this returns. Panics, if one of the jobs panicked.
+	pub fn scoped<'pool, 'scope, F, R>(&'pool mut self, f: F) -> R
+		where F: FnOnce(&Scope<'pool, 'scope>) -> R
+	{
+		let scope = Scope {
+			pool    : self,
+			pending : Cell::new(0),
+			panicked: Cell::new(false),
+			marker  : marker::InvariantLifetime,
+		};
+
+		let result = f(&scope);
+		scope.join();
+
+		if scope.panicked.get() {
+			panic!("A job of the thread pool panicked");
+		}
+
+		result
+	}
+}
+
+#[cfg(feature = "parallel")]
+impl Drop for ThreadPool {
+	fn drop(&mut self) {
+		for _ in range(0, self.threads) {
+			let _ = self.jobs.send(Message::Stop);
+		}
+	}
+}
+
+/// Passes jobs to the threads of a pool. See `ThreadPool::scoped`.
+#[cfg(feature = "parallel")]
+pub struct Scope<'pool, 'scope> {
+	pool    : &'pool mut ThreadPool,
+	pending : Cell<uint>,
+	panicked: Cell<bool>,
+	marker  : marker::InvariantLifetime<'scope>,
+}
+
+#[cfg(feature = "parallel")]
+impl<'pool, 'scope> Scope<'pool, 'scope> {
+	pub fn execute<F>(&self, job: F) where F: FnOnce() + Send + 'scope {
+		let job: Box<Job + Send + 'scope> = box job;
+
+		// The scope waits for all of its jobs before it goes away, so the job
+		// can't outlive anything it borrows.
+		let job: Box<Job + Send + 'static> = unsafe { mem::transmute(job) };
+
+		self.pool.jobs.send(Message::Run(job)).unwrap();
+		self.pending.set(self.pending.get() + 1);
+	}
+
+	fn join(&self) {
+		for _ in range(0, self.pending.get()) {
+			if !self.pool.done.recv().unwrap() {
+				self.panicked.set(true);
+			}
+		}
+
+		self.pending.set(0);
+	}
+}
+
+#[cfg(feature = "parallel")]
+#[unsafe_destructor]
+impl<'pool, 'scope> Drop for Scope<'pool, 'scope> {
+	fn drop(&mut self) {
+		// Only does something, if the closure passed to `ThreadPool::scoped`
+		// panicked. The jobs might still use its data while it's unwinding.
+		self.join();
+	}
+}
+
+#[cfg(feature = "parallel")]
+enum Message {
+	Run(Box<Job + Send>),
+	Stop,
+}
+
+#[cfg(feature = "parallel")]
+trait Job {
+	fn run(self: Box<Self>);
+}
+
+#[cfg(feature = "parallel")]
+impl<F: FnOnce()> Job for F {
+	fn run(self: Box<F>) {
+		(*self)()
+	}
+}
+
+#[cfg(feature = "parallel")]
+fn spawn_worker(jobs: Arc<Mutex<Receiver<Message>>>, done: Sender<bool>) {
+	Thread::spawn(move || {
+		let mut sentinel = Sentinel {
+			jobs  : &jobs,
+			done  : &done,
+			active: true,
+		};
+
+		loop {
+			let message = jobs.lock().unwrap().recv();
+
+			match message {
+				Ok(Message::Run(job)) => {
+					job.run();
+					let _ = done.send(true);
+				},
+				Ok(Message::Stop) | Err(_) =>
+					break,
+			}
+		}
+
+		sentinel.active = false;
+	});
+}
+
+/// Replaces a worker whose job panicked, after reporting the panic.
+#[cfg(feature = "parallel")]
+struct Sentinel<'a> {
+	jobs  : &'a Arc<Mutex<Receiver<Message>>>,
+	done  : &'a Sender<bool>,
+	active: bool,
+}
+
+#[cfg(feature = "parallel")]
+#[unsafe_destructor]
+impl<'a> Drop for Sentinel<'a> {
+	fn drop(&mut self) {
+		if self.active {
+			let _ = self.done.send(false);
+			spawn_worker(self.jobs.clone(), self.done.clone());
+		}
+	}
+}
+
+
+/// Is notified whenever a system is started and whenever it has finished, with
+/// the time in nanoseconds, as returned by `precise_time_ns`. Systems that run
+/// in parallel are reported after all of them have finished, with the times
+/// they actually started and finished at. Only available with the profiling
+/// feature.
+#[cfg(feature = "profiling")]
+pub trait Profiler {
+	fn start(&mut self, system: &str, event: &str, time_ns: u64);
+	fn end(&mut self, system: &str, event: &str, time_ns: u64);
+}
+
+/// Holds the profiler of a `Systems` instance.
+#[cfg(feature = "profiling")]
+pub struct ProfilerHook {
+	profiler: Option<Box<Profiler + 'static>>,
+}
+
+#[cfg(feature = "profiling")]
+impl ProfilerHook {
+	pub fn new() -> ProfilerHook {
+		ProfilerHook {
+			profiler: None,
+		}
+	}
+
+	pub fn set(&mut self, profiler: Box<Profiler + 'static>) {
+		self.profiler = Some(profiler);
+	}
+
+	pub fn take(&mut self) -> Option<Box<Profiler + 'static>> {
+		self.profiler.take()
+	}
+
+	pub fn start(&mut self, system: &str, event: &str, time_ns: u64) {
+		match self.profiler {
+			Some(ref mut profiler) => profiler.start(system, event, time_ns),
+			None                   => (),
+		}
+	}
+
+	pub fn end(&mut self, system: &str, event: &str, time_ns: u64) {
+		match self.profiler {
+			Some(ref mut profiler) => profiler.end(system, event, time_ns),
+			None                   => (),
+		}
+	}
+}
+
+
+/// The durations of all calls to a system, in nanoseconds.
+#[cfg(feature = "profiling")]
+#[deriving(Clone, PartialEq, Show)]
+pub struct Timing {
+	pub calls: u64,
+	pub min  : u64,
+	pub max  : u64,
+	pub total: u64,
+}
+
+#[cfg(feature = "profiling")]
+impl Timing {
+	pub fn avg(&self) -> u64 {
+		self.total / self.calls
+	}
+}
+
+/// A profiler that collects the durations of each system's calls.
+#[cfg(feature = "profiling")]
+#[deriving(Clone, PartialEq, Show)]
+pub struct Timings {
+	started: HashMap<String, u64>,
+	timings: HashMap<String, Timing>,
+}
+
+#[cfg(feature = "profiling")]
+impl Timings {
+	pub fn new() -> Timings {
+		Timings {
+			started: HashMap::new(),
+			timings: HashMap::new(),
+		}
+	}
+
+	pub fn get(&self, system: &str) -> Option<&Timing> {
+		self.timings.get(system)
+	}
+
+	pub fn iter(&self) -> hash_map::Iter<String, Timing> {
+		self.timings.iter()
+	}
+
+	pub fn clear(&mut self) {
+		self.started.clear();
+		self.timings.clear();
+	}
+}
+
+#[cfg(feature = "profiling")]
+impl Profiler for Timings {
+	fn start(&mut self, system: &str, _: &str, time_ns: u64) {
+		self.started.insert(system.to_string(), time_ns);
+	}
+
+	fn end(&mut self, system: &str, _: &str, time_ns: u64) {
+		let started = match self.started.remove(system) {
+			Some(started) => started,
+			None          => return,
+		};
+		let duration = time_ns - started;
+
+		match self.timings.entry(system.to_string()) {
+			hash_map::Entry::Occupied(mut entry) => {
+				let timing = entry.get_mut();
+
+				timing.calls += 1;
+				timing.min    = cmp::min(timing.min, duration);
+				timing.max    = cmp::max(timing.max, duration);
+				timing.total += duration;
+			},
+			hash_map::Entry::Vacant(entry) => {
+				entry.set(Timing {
+					calls: 1,
+					min  : duration,
+					max  : duration,
+					total: duration,
+				});
+			},
+		}
+	}
+}
+
+
+/// Converts the return value of a system into a result. Systems that can't fail
+/// just return `()`.
+pub trait IntoSystemResult<E> {
+	fn into_system_result(self) -> Result<(), E>;
+}
+
+impl<E> IntoSystemResult<E> for () {
+	fn into_system_result(self) -> Result<(), E> {
+		Ok(())
+	}
+}
+
+impl<E> IntoSystemResult<E> for Result<(), E> {
+	fn into_system_result(self) -> Result<(), E> {
+		self
+	}
+}
+
+/// An error that was returned by a system.
+#[deriving(Clone, PartialEq, Show)]
+pub struct SystemError<E> {
+	pub system: &'static str,
+	pub event : &'static str,
+	pub error : E,
+}
+
+/// Decides what happens to the remaining systems for an event, after one of
+/// them has failed.
+#[deriving(Clone, PartialEq, Show)]
+pub enum ErrorPolicy {
+	Abort,
+	Continue,
+}
+
+/// Returned by `dispatch_all` of a world that declares an error type.
+#[deriving(Clone, PartialEq, Show)]
+pub enum DispatchError<E> {
+	LimitExceeded(DispatchLimitExceeded),
+	SystemsFailed(Vec<SystemError<E>>),
+}
+
+
+/// A source of time. Implement it yourself to control time in tests.
+pub trait Clock {
+	fn now_in_s(&mut self) -> f64;
+}
+
+/// The system's high-resolution clock.
+#[deriving(Clone, PartialEq, Show)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now_in_s(&mut self) -> f64 {
+		time::precise_time_s()
+	}
+}
+
+pub const DEFAULT_MAX_STEPS: uint = 8;
+
+/// What needs to be done in a frame: How many fixed steps to update and how far
+/// the remaining time has advanced into the next step, for interpolation.
+#[deriving(Clone, PartialEq, Show)]
+pub struct Frame {
+	pub steps    : uint,
+	pub step_in_s: f64,
+	pub alpha    : f64,
+}
+
+/// Accumulates the time that passes between frames and divides it into steps
+/// of a fixed duration.
+#[deriving(Clone, PartialEq, Show)]
+pub struct FixedTimestep<C> {
+	clock      : C,
+	step_in_s  : f64,
+	max_steps  : uint,
+	accumulated: f64,
+	last_in_s  : Option<f64>,
+}
+
+impl<C: Clock> FixedTimestep<C> {
+	/// Panics, if `step_in_s` isn't positive, as the time could never be
+	/// divided into steps.
+	pub fn new(step_in_s: f64, clock: C) -> FixedTimestep<C> {
+		assert!(step_in_s > 0.0, "A fixed timestep needs a positive step");
+
+		FixedTimestep {
+			clock      : clock,
+			step_in_s  : step_in_s,
+			max_steps  : DEFAULT_MAX_STEPS,
+			accumulated: 0.0,
+			last_in_s  : None,
+		}
+	}
+
+	/// If updating takes longer than the time it simulates, more and more steps
+	/// would pile up. No more than this many steps are done per frame, and the
+	/// rest of the time is dropped. Panics, if `max_steps` is 0, as nothing
+	/// would ever be updated.
+	pub fn set_max_steps(&mut self, max_steps: uint) {
+		assert!(max_steps > 0, "A fixed timestep needs to do at least one step per frame");
+		self.max_steps = max_steps;
+	}
+
+	pub fn clock(&mut self) -> &mut C {
+		&mut self.clock
+	}
+
+	/// Adds the time since the last frame and returns the steps that are due.
+	/// The first frame only starts the clock.
+	pub fn advance(&mut self) -> Frame {
+		let now_in_s = self.clock.now_in_s();
+		match self.last_in_s {
+			Some(last_in_s) => self.accumulated += now_in_s - last_in_s,
+			None            => (),
+		}
+		self.last_in_s = Some(now_in_s);
+
+		let mut steps = 0;
+		while self.accumulated >= self.step_in_s && steps < self.max_steps {
+			self.accumulated -= self.step_in_s;
+			steps += 1;
+		}
+		if steps == self.max_steps {
+			self.accumulated = self.accumulated % self.step_in_s;
+		}
+
+		Frame {
+			steps    : steps,
+			step_in_s: self.step_in_s,
+			alpha    : self.accumulated / self.step_in_s,
+		}
+	}
+}
@@ -0,0 +1,56 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position, Velocity;
+	resources  Speed;
+
+	events Update();
+
+	system integrate on(Update) with(&mut Position, &Velocity, &Speed);
+}
+
+
+pub type Position = i32;
+pub type Velocity = i32;
+pub type Speed    = i32;
+
+
+fn integrate(
+	positions : &mut Components<Position>,
+	velocities: &Components<Velocity>,
+	speed     : &Speed
+) {
+	for (id, position) in positions.iter_mut() {
+		*position += velocities[*id] * *speed;
+	}
+}
+
+
+#[test]
+fn it_should_pass_arguments_with_the_declared_access() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	entities.speed = 2;
+	let id = entities.add(
+		Entity::new()
+			.with_position(0)
+			.with_velocity(3)
+	);
+
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(6, entities.positions[id]);
+	assert_eq!(3, entities.velocities[id]);
+}
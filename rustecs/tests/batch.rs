@@ -0,0 +1,81 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Control,
+	EntityContainer,
+};
+
+
+world! {
+	components Component;
+}
+
+pub type Component = u32;
+
+
+#[test]
+fn it_should_add_entities_in_batches() {
+	let mut entities = Entities::new();
+
+	let ids = entities.add_batch(
+		range(0, 3u32).map(|i| Entity::new().with_component(i))
+	);
+
+	assert_eq!(3, ids.len());
+	assert_eq!(3, entities.components.len());
+	for (i, id) in ids.iter().enumerate() {
+		assert_eq!(i as u32, entities.components[*id]);
+	}
+}
+
+#[test]
+fn it_should_remove_entities_in_batches() {
+	let mut entities = Entities::new();
+
+	let ids = entities.add_batch(
+		range(0, 3u32).map(|i| Entity::new().with_component(i))
+	);
+	entities.remove_batch(ids.into_iter().take(2));
+
+	assert_eq!(1, entities.components.len());
+}
+
+#[test]
+fn it_should_extend_entities() {
+	let mut entities = Entities::new();
+
+	entities.extend(
+		range(0, 3u32).map(|i| Entity::new().with_component(i))
+	);
+
+	assert_eq!(3, entities.components.len());
+}
+
+#[test]
+fn it_should_add_and_remove_batches_after_apply() {
+	let mut entities = Entities::new();
+	let mut control  = Control::new();
+
+	let ids = control.add_batch(
+		range(0, 3u32).map(|i| Entity::new().with_component(i))
+	);
+	control.extend(
+		range(0, 2u32).map(|i| Entity::new().with_component(i))
+	);
+
+	assert_eq!(0, entities.components.len());
+
+	control.apply(&mut entities);
+
+	assert_eq!(5, entities.components.len());
+
+	control.remove_batch(ids.into_iter());
+	control.apply(&mut entities);
+
+	assert_eq!(2, entities.components.len());
+}
@@ -0,0 +1,66 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Control,
+	EntityContainer,
+};
+
+
+world! {
+	components Alpha, Beta;
+
+	derived_traits Clone;
+}
+
+
+pub type Alpha = u32;
+pub type Beta  = String;
+
+
+#[test]
+fn it_should_duplicate_entities() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(
+		Entity::new()
+			.with_alpha(5)
+			.with_beta("beta".to_string())
+	);
+
+	let copy_id = entities.duplicate(id).unwrap();
+
+	assert!(copy_id != id);
+	assert_eq!(5                 , entities.alphas[copy_id]);
+	assert_eq!("beta".to_string(), entities.betas[copy_id]);
+	assert_eq!(5                 , entities.alphas[id]);
+}
+
+#[test]
+fn it_should_not_duplicate_entities_that_dont_exist() {
+	let mut entities = Entities::new();
+
+	assert_eq!(None, entities.duplicate(5));
+	assert_eq!(0   , entities.alphas.len());
+}
+
+#[test]
+fn it_should_duplicate_entities_after_apply() {
+	let mut entities = Entities::new();
+	let mut control  = Control::new();
+
+	let id = entities.add(Entity::new().with_alpha(5));
+
+	let copy_id = control.duplicate(id);
+
+	assert_eq!(1, entities.alphas.len());
+
+	control.apply(&mut entities);
+
+	assert_eq!(2, entities.alphas.len());
+	assert_eq!(5, entities.alphas[copy_id]);
+}
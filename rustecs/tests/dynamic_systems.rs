@@ -0,0 +1,75 @@
+#![feature(phase, unboxed_closures)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position;
+
+	events Init(), Update();
+
+	system update on(Update) with(Position);
+}
+
+
+pub type Position = i32;
+
+
+fn update(positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += 1;
+	}
+}
+
+
+#[test]
+fn it_should_call_registered_systems_after_declared_ones() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(1));
+
+	systems.register(
+		EventKind::Update,
+		box |&mut: _: &mut Event, entities: &mut Entities| {
+			for (_, position) in entities.positions.iter_mut() {
+				*position *= 10;
+			}
+		}
+	);
+
+	systems.trigger_init(&mut entities);
+	assert_eq!(1, entities.positions[id]);
+
+	systems.trigger_update(&mut entities);
+	assert_eq!(20, entities.positions[id]);
+}
+
+#[test]
+fn it_should_not_call_unregistered_systems() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(1));
+
+	let handle = systems.register(
+		EventKind::Init,
+		box |&mut: _: &mut Event, entities: &mut Entities| {
+			entities.positions.clear();
+		}
+	);
+
+	assert!(systems.unregister(handle.clone()));
+	assert!(!systems.unregister(handle));
+
+	systems.trigger_init(&mut entities);
+	assert_eq!(1, entities.positions[id]);
+}
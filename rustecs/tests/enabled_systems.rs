@@ -0,0 +1,57 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position;
+
+	events Update();
+
+	system move_forward on(Update) with(Position);
+	system move_back    on(Update) with(Position);
+}
+
+
+pub type Position = i32;
+
+
+fn move_forward(positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += 10;
+	}
+}
+
+fn move_back(positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position -= 1;
+	}
+}
+
+
+#[test]
+fn it_should_not_call_disabled_systems() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(0));
+
+	assert!(systems.is_enabled(SystemName::MoveForward));
+
+	systems.set_enabled(SystemName::MoveForward, false);
+	systems.trigger_update(&mut entities);
+	assert_eq!(-1, entities.positions[id]);
+	assert!(!systems.is_enabled(SystemName::MoveForward));
+
+	systems.set_enabled(SystemName::MoveForward, true);
+	systems.trigger_update(&mut entities);
+	assert_eq!(8, entities.positions[id]);
+}
@@ -5,7 +5,10 @@ extern crate rustecs;
 #[phase(plugin)] extern crate rustecs_macros;
 
 
-use rustecs::EntityContainer;
+use rustecs::{
+	EntityContainer,
+	EntityId,
+};
 
 
 world! {
@@ -79,3 +82,56 @@ fn it_should_destroy_entities() {
 	assert_eq!(0, entities.scores.len());
 	assert_eq!(0, entities.weapon_states.len());
 }
+
+#[test]
+fn it_should_keep_track_of_its_entities() {
+	let mut entities = Entities::with_capacity(2);
+
+	assert!(entities.is_empty());
+
+	let id_1 = entities.add(Entity::new().with_score(1));
+	let id_2 = entities.add(Entity::new().with_score(2));
+
+	assert_eq!(2, entities.len());
+	assert!(entities.contains(id_1));
+	assert!(entities.contains(id_2));
+
+	let mut ids: Vec<EntityId> = entities.ids().collect();
+	ids.sort();
+	assert_eq!(vec![id_1, id_2], ids);
+
+	entities.remove(id_1);
+
+	assert_eq!(1, entities.len());
+	assert!(!entities.contains(id_1));
+}
+
+#[test]
+fn it_should_not_reuse_ids_after_clear() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(Entity::new().with_score(1));
+	entities.clear();
+
+	assert!(entities.is_empty());
+	assert_eq!(0, entities.scores.len());
+
+	let new_id = entities.add(Entity::new().with_score(2));
+
+	assert!(new_id != id);
+}
+
+#[test]
+fn it_should_reuse_ids_after_reset() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(Entity::new().with_score(1));
+	entities.reset();
+
+	assert!(entities.is_empty());
+	assert_eq!(0, entities.scores.len());
+
+	let new_id = entities.add(Entity::new().with_score(2));
+
+	assert_eq!(id, new_id);
+}
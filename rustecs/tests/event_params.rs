@@ -0,0 +1,99 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position, Name;
+
+	events Init(), Update(delta_time_in_s: f64, factor: f64), Rename(name: String);
+
+	system init   on(Init)   with(Position);
+	system update on(Update) with(Position);
+	system rename on(Rename) with(Name);
+}
+
+
+pub type Position = f64;
+pub type Name     = String;
+
+
+fn init(positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position = 1.0;
+	}
+}
+
+fn update(
+	delta_time_in_s: &f64,
+	factor         : &f64,
+	positions      : &mut Components<Position>
+) {
+	for (_, position) in positions.iter_mut() {
+		*position += *delta_time_in_s * *factor;
+	}
+}
+
+fn rename(name: &String, names: &mut Components<Name>) {
+	for (_, old_name) in names.iter_mut() {
+		*old_name = name.clone();
+	}
+}
+
+
+#[test]
+fn it_should_generate_event_structs() {
+	let _init   = Init;
+	let  update = Update { delta_time_in_s: 0.5, factor: 2.0 };
+
+	assert_eq!(0.5, update.delta_time_in_s);
+	assert_eq!(2.0, update.factor);
+}
+
+#[test]
+fn it_should_pass_event_parameters_to_systems() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(0.0));
+
+	systems.trigger(Event::Init(&mut Init), &mut entities);
+	assert_eq!(1.0, entities.positions[id]);
+
+	let mut update = Update { delta_time_in_s: 0.5, factor: 2.0 };
+	systems.trigger(Event::Update(&mut update), &mut entities);
+	assert_eq!(2.0, entities.positions[id]);
+}
+
+#[test]
+fn it_should_generate_trigger_methods() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(0.0));
+
+	systems.trigger_init(&mut entities);
+	systems.trigger_update(0.5, 2.0, &mut entities);
+
+	assert_eq!(2.0, entities.positions[id]);
+}
+
+#[test]
+fn it_should_pass_parameters_that_are_not_copy() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_name("old".to_string()));
+
+	systems.trigger_rename("new".to_string(), &mut entities);
+
+	assert_eq!("new".to_string(), entities.names[id]);
+}
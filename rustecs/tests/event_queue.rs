@@ -0,0 +1,97 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	DispatchLimitExceeded,
+	EntityContainer,
+	EventQueue,
+};
+
+
+world! {
+	components Health, DeathCount;
+
+	events Hit(damage: u32), Death(), Ping();
+
+	system damage on(Hit)   with(Health, events);
+	system die    on(Death) with(DeathCount);
+	system pong   on(Ping)  with(events);
+}
+
+
+pub type Health     = u32;
+pub type DeathCount = u32;
+
+
+fn damage(
+	damage : &u32,
+	healths: &mut Components<Health>,
+	events : &mut EventQueue<OwnedEvent>
+) {
+	for (_, health) in healths.iter_mut() {
+		*health -= *damage;
+
+		if *health == 0 {
+			events.push(OwnedEvent::Death(Death));
+		}
+	}
+}
+
+fn die(counts: &mut Components<DeathCount>) {
+	for (_, count) in counts.iter_mut() {
+		*count += 1;
+	}
+}
+
+fn pong(events: &mut EventQueue<OwnedEvent>) {
+	events.push(OwnedEvent::Ping(Ping));
+}
+
+
+#[test]
+fn it_should_dispatch_queued_events() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_death_count(0));
+
+	systems.events().push(OwnedEvent::Death(Death));
+	systems.events().push(OwnedEvent::Death(Death));
+
+	assert_eq!(Ok(2), systems.dispatch_all(&mut entities));
+	assert_eq!(2, entities.death_counts[id]);
+	assert!(systems.events().is_empty());
+}
+
+#[test]
+fn it_should_dispatch_events_queued_by_systems() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	entities.add(Entity::new().with_health(5));
+	let id = entities.add(Entity::new().with_death_count(0));
+
+	systems.events().push(OwnedEvent::Hit(Hit { damage: 5 }));
+
+	assert_eq!(Ok(2), systems.dispatch_all(&mut entities));
+	assert_eq!(1, entities.death_counts[id]);
+}
+
+#[test]
+fn it_should_stop_dispatching_when_the_limit_is_reached() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	systems.set_dispatch_limit(10);
+	systems.events().push(OwnedEvent::Ping(Ping));
+
+	assert_eq!(
+		Err(DispatchLimitExceeded { dispatched: 10, remaining: 1 }),
+		systems.dispatch_all(&mut entities)
+	);
+}
@@ -0,0 +1,93 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+	ErrorPolicy,
+	SystemError,
+};
+
+
+world! {
+	components Position;
+
+	events Update();
+
+	system validate on(Update) with(&Position);
+	system advance  on(Update) with(Position);
+
+	error String;
+}
+
+
+pub type Position = i32;
+
+
+fn validate(positions: &Components<Position>) -> Result<(), String> {
+	for (_, &position) in positions.iter() {
+		if position < 0 {
+			return Err(format!("Invalid position: {}", position));
+		}
+	}
+
+	Ok(())
+}
+
+fn advance(positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += 1;
+	}
+}
+
+
+#[test]
+fn it_should_return_ok_if_no_system_fails() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(0));
+
+	assert_eq!(Ok(()), systems.trigger_update(&mut entities));
+	assert_eq!(1, entities.positions[id]);
+}
+
+#[test]
+fn it_should_skip_the_remaining_systems_after_an_error() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(-5));
+
+	let result = systems.trigger_update(&mut entities);
+
+	assert_eq!(
+		Err(vec![
+			SystemError {
+				system: "validate",
+				event : "Update",
+				error : "Invalid position: -5".to_string(),
+			},
+		]),
+		result
+	);
+	assert_eq!(-5, entities.positions[id]);
+}
+
+#[test]
+fn it_should_call_the_remaining_systems_if_told_to_continue() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(-5));
+
+	systems.set_error_policy(ErrorPolicy::Continue);
+	let result = systems.trigger_update(&mut entities);
+
+	assert!(result.is_err());
+	assert_eq!(-4, entities.positions[id]);
+}
@@ -0,0 +1,118 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Clock,
+	Components,
+	EntityContainer,
+	FixedTimestep,
+	Frame,
+};
+
+
+world! {
+	components Position;
+	resources  Alpha;
+
+	events Update(step_in_s: f64), Render(alpha: f64);
+
+	system update on(Update) with(Position);
+	system render on(Render) with(Alpha);
+
+	fixed_timestep Update, Render;
+}
+
+
+pub type Position = f64;
+pub type Alpha    = f64;
+
+
+fn update(step_in_s: &f64, positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += *step_in_s;
+	}
+}
+
+fn render(alpha: &f64, last_alpha: &mut Alpha) {
+	*last_alpha = *alpha;
+}
+
+
+struct ManualClock {
+	now_in_s: f64,
+}
+
+impl Clock for ManualClock {
+	fn now_in_s(&mut self) -> f64 {
+		self.now_in_s
+	}
+}
+
+
+#[test]
+fn it_should_divide_elapsed_time_into_fixed_steps() {
+	let mut timestep = FixedTimestep::new(0.25, ManualClock { now_in_s: 0.0 });
+
+	assert_eq!(Frame { steps: 0, step_in_s: 0.25, alpha: 0.0 }, timestep.advance());
+
+	timestep.clock().now_in_s = 0.625;
+	assert_eq!(Frame { steps: 2, step_in_s: 0.25, alpha: 0.5 }, timestep.advance());
+
+	timestep.clock().now_in_s = 0.75;
+	assert_eq!(Frame { steps: 1, step_in_s: 0.25, alpha: 0.0 }, timestep.advance());
+}
+
+#[test]
+fn it_should_limit_the_steps_per_frame() {
+	let mut timestep = FixedTimestep::new(0.25, ManualClock { now_in_s: 0.0 });
+	timestep.set_max_steps(2);
+	timestep.advance();
+
+	timestep.clock().now_in_s = 10.125;
+	assert_eq!(Frame { steps: 2, step_in_s: 0.25, alpha: 0.5 }, timestep.advance());
+}
+
+#[test]
+fn it_should_drop_the_time_beyond_the_limit_after_a_large_delta() {
+	let mut timestep = FixedTimestep::new(0.25, ManualClock { now_in_s: 0.0 });
+	timestep.advance();
+
+	timestep.clock().now_in_s = 1000000000.125;
+	assert_eq!(Frame { steps: 8, step_in_s: 0.25, alpha: 0.5 }, timestep.advance());
+
+	timestep.clock().now_in_s = 1000000000.375;
+	assert_eq!(Frame { steps: 1, step_in_s: 0.25, alpha: 0.5 }, timestep.advance());
+}
+
+#[test]
+#[should_fail]
+fn it_should_reject_a_limit_of_zero_steps() {
+	let mut timestep = FixedTimestep::new(0.25, ManualClock { now_in_s: 0.0 });
+	timestep.set_max_steps(0);
+}
+
+#[test]
+#[should_fail]
+fn it_should_reject_a_step_of_zero_seconds() {
+	FixedTimestep::new(0.0, ManualClock { now_in_s: 0.0 });
+}
+
+#[test]
+fn it_should_trigger_update_and_render_events() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+	let mut timestep = FixedTimestep::new(0.25, ManualClock { now_in_s: 0.0 });
+
+	let id = entities.add(Entity::new().with_position(0.0));
+
+	systems.run_fixed_timestep(&mut timestep, &mut entities);
+	timestep.clock().now_in_s = 0.625;
+	systems.run_fixed_timestep(&mut timestep, &mut entities);
+
+	assert_eq!(0.5, entities.positions[id]);
+	assert_eq!(0.5, entities.alpha);
+}
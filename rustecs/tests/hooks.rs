@@ -0,0 +1,107 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Control,
+	EntityContainer,
+	EntityId,
+};
+
+
+world! {
+	components Collider, Sprite;
+	resources  Log;
+
+	on_add(Collider)    = register_body;
+	on_remove(Collider) = unregister_body;
+	on_remove(Sprite)   = free_texture;
+}
+
+
+pub type Collider = u32;
+pub type Sprite   = u32;
+pub type Log      = Vec<String>;
+
+
+fn register_body(id: EntityId, entities: &mut Entities) {
+	let collider = entities.colliders[id];
+	entities.log.push(format!("add {} {}", id, collider));
+}
+
+fn unregister_body(id: EntityId, collider: &Collider, entities: &mut Entities) {
+	entities.log.push(format!("remove {} {}", id, *collider));
+}
+
+fn free_texture(id: EntityId, sprite: &Sprite, entities: &mut Entities) {
+	entities.log.push(format!("free {} {}", id, *sprite));
+}
+
+
+#[test]
+fn it_should_call_hooks_when_adding_and_removing_entities() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(Entity::new().with_collider(5).with_sprite(7));
+	entities.add(Entity::new().with_sprite(8));
+	assert_eq!(vec![format!("add {} 5", id)], entities.log);
+
+	entities.log.clear();
+	entities.remove(id);
+
+	let mut log = entities.log.clone();
+	log.sort();
+	assert_eq!(vec![format!("free {} 7", id), format!("remove {} 5", id)], log);
+}
+
+#[test]
+fn it_should_call_hooks_when_importing_entities() {
+	let mut entities = Entities::new();
+
+	entities.import(3, Entity::new().with_collider(5));
+	entities.import(3, Entity::new().with_collider(6));
+
+	assert_eq!(
+		vec![
+			"add 3 5".to_string(),
+			"remove 3 5".to_string(),
+			"add 3 6".to_string(),
+		],
+		entities.log
+	);
+}
+
+#[test]
+fn it_should_call_hooks_when_inserting_and_removing_components() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(Entity::new());
+
+	assert!(entities.insert_collider(id, 5));
+	assert_eq!(Some(5), entities.remove_collider(id));
+	assert!(!entities.insert_collider(id + 100, 5));
+
+	assert_eq!(
+		vec![format!("add {} 5", id), format!("remove {} 5", id)],
+		entities.log
+	);
+}
+
+#[test]
+fn it_should_call_hooks_when_applying_a_control() {
+	let mut entities = Entities::new();
+	let mut control  = Control::new();
+
+	let id = control.add(Entity::new().with_collider(5));
+	control.apply(&mut entities);
+	control.remove(id);
+	control.apply(&mut entities);
+
+	assert_eq!(
+		vec![format!("add {} 5", id), format!("remove {} 5", id)],
+		entities.log
+	);
+}
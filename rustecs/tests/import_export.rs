@@ -9,13 +9,14 @@ use rustecs::EntityContainer;
 
 
 world! {
-	components Component;
+	components Component, Marker;
 
 	derived_traits Clone, PartialEq, Show;
 }
 
 
 pub type Component = u32;
+pub type Marker    = bool;
 
 
 #[test]
@@ -55,3 +56,18 @@ fn it_should_import_entities() {
 	assert_eq!(1, entities.components.len());
 	assert_eq!(8, entities.components[entity_id]);
 }
+
+#[test]
+fn it_should_replace_existing_entities_on_import() {
+	let mut entities = Entities::new();
+
+	let entity_id = entities.add(
+		Entity::new()
+			.with_component(5)
+			.with_marker(true)
+	);
+	entities.import(entity_id, Entity::new().with_component(8));
+
+	assert_eq!(8    , entities.components[entity_id]);
+	assert_eq!(false, entities.markers.contains_key(&entity_id));
+}
@@ -0,0 +1,95 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Control,
+	EntityContainer,
+	ImportPolicy,
+};
+
+
+world! {
+	components Alpha, Beta;
+
+	import_policy Merge;
+}
+
+
+pub type Alpha = u32;
+pub type Beta  = u32;
+
+
+#[test]
+fn it_should_use_the_import_policy_of_the_world() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(Entity::new().with_alpha(1).with_beta(2));
+	let imported = entities.import(id, Entity::new().with_alpha(3));
+
+	assert!(imported);
+	assert_eq!(3, entities.alphas[id]);
+	assert_eq!(2, entities.betas[id]);
+}
+
+#[test]
+fn it_should_replace_entities_on_import() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(Entity::new().with_alpha(1).with_beta(2));
+	let imported = entities.import_with(
+		id,
+		Entity::new().with_alpha(3),
+		ImportPolicy::Replace,
+	);
+
+	assert!(imported);
+	assert_eq!(3    , entities.alphas[id]);
+	assert_eq!(false, entities.betas.contains_key(&id));
+}
+
+#[test]
+fn it_should_reject_entities_on_import() {
+	let mut entities = Entities::new();
+
+	let id = entities.add(Entity::new().with_alpha(1).with_beta(2));
+	let imported = entities.import_with(
+		id,
+		Entity::new().with_alpha(3),
+		ImportPolicy::Reject,
+	);
+
+	assert!(!imported);
+	assert_eq!(1, entities.alphas[id]);
+	assert_eq!(2, entities.betas[id]);
+}
+
+#[test]
+fn it_should_only_reject_entities_whose_id_is_in_use() {
+	let mut entities = Entities::new();
+
+	let imported = entities.import_with(
+		5,
+		Entity::new().with_alpha(3),
+		ImportPolicy::Reject,
+	);
+
+	assert!(imported);
+	assert_eq!(3, entities.alphas[5]);
+}
+
+#[test]
+fn it_should_apply_the_import_policy_given_to_control() {
+	let mut entities = Entities::new();
+	let mut control  = Control::new();
+
+	let id = entities.add(Entity::new().with_alpha(1).with_beta(2));
+	control.import_with(id, Entity::new().with_alpha(3), ImportPolicy::Replace);
+	control.apply(&mut entities);
+
+	assert_eq!(3    , entities.alphas[id]);
+	assert_eq!(false, entities.betas.contains_key(&id));
+}
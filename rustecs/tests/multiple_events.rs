@@ -0,0 +1,59 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position, Transform;
+
+	events Init(), Update(delta: i32);
+
+	system sync_transforms on(Init, Update) with(&Position, Transform);
+}
+
+
+pub type Position  = i32;
+pub type Transform = i32;
+
+
+fn sync_transforms(
+	event     : &mut Event,
+	positions : &Components<Position>,
+	transforms: &mut Components<Transform>
+) {
+	let offset = match *event {
+		Event::Init(_)            => 0,
+		Event::Update(ref update) => update.delta,
+	};
+
+	for (id, transform) in transforms.iter_mut() {
+		*transform = positions[*id] + offset;
+	}
+}
+
+
+#[test]
+fn it_should_call_systems_for_each_of_their_events() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(
+		Entity::new()
+			.with_position(5)
+			.with_transform(0)
+	);
+
+	systems.trigger_init(&mut entities);
+	assert_eq!(5, entities.transforms[id]);
+
+	systems.trigger_update(2, &mut entities);
+	assert_eq!(7, entities.transforms[id]);
+}
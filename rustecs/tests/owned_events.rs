@@ -0,0 +1,75 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position;
+
+	events Update(delta_time_in_s: f64);
+
+	system update on(Update) with(Position);
+
+	derived_traits Clone, PartialEq, Show;
+}
+
+
+pub type Position = f64;
+
+
+fn update(delta_time_in_s: &f64, positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += *delta_time_in_s;
+	}
+}
+
+
+#[test]
+fn it_should_trigger_owned_events() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(0.0));
+
+	systems.trigger(
+		OwnedEvent::Update(Update { delta_time_in_s: 0.5 }),
+		&mut entities,
+	);
+
+	assert_eq!(0.5, entities.positions[id]);
+}
+
+#[test]
+fn it_should_convert_between_owned_and_borrowed_events() {
+	let mut owned = OwnedEvent::Update(Update { delta_time_in_s: 0.5 });
+
+	let converted = owned.as_event().to_owned();
+
+	assert_eq!(owned, converted);
+}
+
+#[test]
+fn it_should_store_owned_events() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(0.0));
+
+	let recorded = vec![
+		OwnedEvent::Update(Update { delta_time_in_s: 0.5 }),
+		OwnedEvent::Update(Update { delta_time_in_s: 1.0 }),
+	];
+	for event in recorded.into_iter() {
+		systems.trigger(event, &mut entities);
+	}
+
+	assert_eq!(1.5, entities.positions[id]);
+}
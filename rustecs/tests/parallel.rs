@@ -0,0 +1,158 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use std::io::timer::sleep;
+use std::sync::atomic::{
+	AtomicUint,
+	ATOMIC_UINT_INIT,
+	Ordering,
+};
+use std::time::Duration;
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position, Velocity, Score;
+	resources  Gravity;
+
+	events Update(), Read(), Write();
+
+	system gravity     on(Update) with(&Gravity, Velocity);
+	system integrate   on(Update) with(Position, &Velocity);
+	system count_score on(Update) with(Score);
+
+	system read_a  on(Read)  with(&Gravity);
+	system read_b  on(Read)  with(&Gravity);
+	system write_a on(Write) with(Score);
+	system write_b on(Write) with(Score);
+}
+
+
+pub type Position = i32;
+pub type Velocity = i32;
+pub type Score    = i32;
+pub type Gravity  = i32;
+
+
+fn gravity(gravity: &Gravity, velocities: &mut Components<Velocity>) {
+	for (_, velocity) in velocities.iter_mut() {
+		*velocity -= *gravity;
+	}
+}
+
+fn integrate(
+	positions : &mut Components<Position>,
+	velocities: &Components<Velocity>
+) {
+	for (id, position) in positions.iter_mut() {
+		*position += velocities[*id];
+	}
+}
+
+fn count_score(scores: &mut Components<Score>) {
+	for (_, score) in scores.iter_mut() {
+		*score += 1;
+	}
+}
+
+
+static READS_RUNNING : AtomicUint = ATOMIC_UINT_INIT;
+static READS_MAX     : AtomicUint = ATOMIC_UINT_INIT;
+static WRITES_RUNNING: AtomicUint = ATOMIC_UINT_INIT;
+static WRITES_MAX    : AtomicUint = ATOMIC_UINT_INIT;
+
+fn read_a(_: &Gravity) {
+	track(&READS_RUNNING, &READS_MAX);
+}
+
+fn read_b(_: &Gravity) {
+	track(&READS_RUNNING, &READS_MAX);
+}
+
+fn write_a(_: &mut Components<Score>) {
+	track(&WRITES_RUNNING, &WRITES_MAX);
+}
+
+fn write_b(_: &mut Components<Score>) {
+	track(&WRITES_RUNNING, &WRITES_MAX);
+}
+
+// Records how many of the tracked systems are running at the same time. Waits
+// for the other system to start, but gives up after a second, so the tests
+// fail instead of hanging, if the systems don't run in parallel.
+fn track(running: &AtomicUint, max: &AtomicUint) {
+	running.fetch_add(1, Ordering::SeqCst);
+
+	for _ in range(0u, 100) {
+		if running.load(Ordering::SeqCst) >= 2 {
+			break;
+		}
+		sleep(Duration::milliseconds(10));
+	}
+
+	let current = running.load(Ordering::SeqCst);
+	loop {
+		let previous = max.load(Ordering::SeqCst);
+		if current <= previous
+			|| max.compare_and_swap(previous, current, Ordering::SeqCst) == previous
+		{
+			break;
+		}
+	}
+
+	running.fetch_sub(1, Ordering::SeqCst);
+}
+
+
+#[test]
+fn it_should_keep_the_order_of_conflicting_systems() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	entities.gravity = 1;
+	let id = entities.add(
+		Entity::new()
+			.with_position(0)
+			.with_velocity(5)
+			.with_score(10)
+	);
+
+	systems.trigger_update(&mut entities);
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(3 , entities.velocities[id]);
+	assert_eq!(7 , entities.positions[id]);
+	assert_eq!(12, entities.scores[id]);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn it_should_run_non_conflicting_systems_at_the_same_time() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	systems.set_threads(2);
+	systems.trigger_read(&mut entities);
+
+	assert_eq!(2, READS_MAX.load(Ordering::SeqCst));
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn it_should_not_run_conflicting_systems_at_the_same_time() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	systems.set_threads(2);
+	systems.trigger_write(&mut entities);
+
+	assert_eq!(1, WRITES_MAX.load(Ordering::SeqCst));
+}
@@ -0,0 +1,77 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Control,
+	EntityContainer,
+};
+
+
+world! {
+	components Position, Health;
+
+	prefab Ship   = Position(Default), Health(100);
+	prefab Marker = Position(Position(3, 5));
+}
+
+
+#[deriving(Default, PartialEq, Show)]
+pub struct Position(i16, i16);
+
+pub type Health = u16;
+
+
+#[test]
+fn it_should_generate_prefab_constructors() {
+	let ship = Entity::ship();
+
+	assert_eq!(Some(Position(0, 0)), ship.position);
+	assert_eq!(Some(100)           , ship.health);
+
+	let marker = Entity::marker();
+
+	assert_eq!(Some(Position(3, 5)), marker.position);
+	assert_eq!(None                , marker.health);
+}
+
+#[test]
+fn it_should_spawn_prefabs() {
+	let mut entities = Entities::new();
+
+	let id = entities.spawn_ship();
+
+	assert_eq!(Position(0, 0), entities.positions[id]);
+	assert_eq!(100           , entities.healths[id]);
+}
+
+#[test]
+fn it_should_spawn_prefabs_through_control() {
+	let mut entities = Entities::new();
+	let mut control  = Control::new();
+
+	let id = control.spawn_marker();
+
+	assert_eq!(0, entities.positions.len());
+
+	control.apply(&mut entities);
+
+	assert_eq!(Position(3, 5), entities.positions[id]);
+}
+
+#[test]
+fn it_should_enumerate_prefabs_by_name() {
+	let mut entities = Entities::new();
+
+	assert_eq!(vec![Prefab::Ship, Prefab::Marker], Prefab::all());
+	assert_eq!("Ship", Prefab::Ship.name());
+	assert_eq!(Some(Prefab::Marker), Prefab::from_name("Marker"));
+	assert_eq!(None, Prefab::from_name("Missile"));
+
+	let id = entities.spawn(Prefab::from_name("Ship").unwrap());
+
+	assert_eq!(100, entities.healths[id]);
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "profiling")]
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rustecs::{
+	Components,
+	EntityContainer,
+	Profiler,
+};
+
+
+world! {
+	components Position;
+
+	events Init(), Update();
+
+	system init   on(Init)   with(Position);
+	system update on(Update) with(Position);
+}
+
+
+pub type Position = i32;
+
+
+fn init(_: &mut Components<Position>) {}
+
+fn update(positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += 1;
+	}
+}
+
+
+struct Recorder {
+	calls: Rc<RefCell<Vec<String>>>,
+}
+
+impl Profiler for Recorder {
+	fn start(&mut self, system: &str, event: &str, _: u64) {
+		self.calls.borrow_mut().push(format!("start {} {}", system, event));
+	}
+
+	fn end(&mut self, system: &str, event: &str, _: u64) {
+		self.calls.borrow_mut().push(format!("end {} {}", system, event));
+	}
+}
+
+
+#[test]
+fn it_should_notify_the_profiler() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let calls = Rc::new(RefCell::new(Vec::new()));
+	systems.set_profiler(box Recorder { calls: calls.clone() });
+
+	systems.trigger_init(&mut entities);
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(
+		vec![
+			"start init Init".to_string(),
+			"end init Init".to_string(),
+			"start update Update".to_string(),
+			"end update Update".to_string(),
+		],
+		*calls.borrow()
+	);
+
+	assert!(systems.remove_profiler().is_some());
+	systems.trigger_init(&mut entities);
+	assert_eq!(4, calls.borrow().len());
+}
+
+#[test]
+fn it_should_collect_timings() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	entities.add(Entity::new().with_position(0));
+
+	assert!(systems.timings().is_none());
+
+	systems.set_collect_timings(true);
+	systems.trigger_update(&mut entities);
+	systems.trigger_update(&mut entities);
+
+	let timings = systems.timings().unwrap();
+	let timing  = timings.get("update").unwrap();
+
+	assert_eq!(2, timing.calls);
+	assert!(timing.min <= timing.avg());
+	assert!(timing.avg() <= timing.max);
+	assert!(timings.get("init").is_none());
+}
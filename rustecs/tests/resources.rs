@@ -0,0 +1,73 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position;
+	resources  Tick, Config;
+
+	events Update();
+
+	system update on(Update) with(Tick, Position, Config);
+}
+
+
+pub type Position = u32;
+pub type Tick     = u32;
+
+#[deriving(Default)]
+pub struct Config {
+	pub speed: u32,
+}
+
+
+fn update(tick: &mut Tick, positions: &mut Components<Position>, config: &mut Config) {
+	*tick += 1;
+
+	for (_, position) in positions.iter_mut() {
+		*position += config.speed;
+	}
+}
+
+
+#[test]
+fn it_should_initialize_resources_with_their_defaults() {
+	let entities = Entities::new();
+
+	assert_eq!(0, entities.tick);
+	assert_eq!(0, entities.config.speed);
+}
+
+#[test]
+fn it_should_pass_resources_to_systems() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	entities.config = Config { speed: 2 };
+	let id = entities.add(Entity::new().with_position(0));
+
+	systems.trigger_update(&mut entities);
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(2, entities.tick);
+	assert_eq!(4, entities.positions[id]);
+}
+
+#[test]
+fn it_should_keep_resources_when_clearing_entities() {
+	let mut entities = Entities::new();
+
+	entities.tick = 5;
+	entities.clear();
+
+	assert_eq!(5, entities.tick);
+}
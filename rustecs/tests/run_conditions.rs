@@ -0,0 +1,66 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position;
+	resources  Paused;
+
+	events Update(steps: i32);
+
+	system move_forward on(Update) with(Position) when(not_paused);
+	system move_far     on(Update) with(Position) when(not_paused, is_big_step);
+}
+
+
+pub type Position = i32;
+pub type Paused   = bool;
+
+
+fn not_paused(_: &Update, entities: &Entities) -> bool {
+	!entities.paused
+}
+
+fn is_big_step(update: &Update, _: &Entities) -> bool {
+	update.steps > 1
+}
+
+fn move_forward(steps: &i32, positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += *steps;
+	}
+}
+
+fn move_far(steps: &i32, positions: &mut Components<Position>) {
+	for (_, position) in positions.iter_mut() {
+		*position += *steps * 10;
+	}
+}
+
+
+#[test]
+fn it_should_only_call_systems_whose_conditions_hold() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_position(0));
+
+	systems.trigger_update(1, &mut entities);
+	assert_eq!(1, entities.positions[id]);
+
+	systems.trigger_update(2, &mut entities);
+	assert_eq!(23, entities.positions[id]);
+
+	entities.paused = true;
+	systems.trigger_update(2, &mut entities);
+	assert_eq!(23, entities.positions[id]);
+}
@@ -0,0 +1,99 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	ApplyControl,
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Log;
+
+	events Input(), Update(delta_time_in_s: f64), Render;
+
+	system render on(Render) with(Log);
+	system update on(Update) with(Log);
+	system input  on(Input)  with(Log);
+
+	schedule Frame = Input, Update, Render;
+}
+
+
+pub type Log = Vec<String>;
+
+pub struct Render;
+
+
+fn render(_: &mut Render, logs: &mut Components<Log>) {
+	for (_, log) in logs.iter_mut() {
+		log.push("render".to_string());
+	}
+}
+
+fn update(delta_time_in_s: &f64, logs: &mut Components<Log>) {
+	for (_, log) in logs.iter_mut() {
+		log.push(format!("update {}", delta_time_in_s));
+	}
+}
+
+fn input(logs: &mut Components<Log>) {
+	for (_, log) in logs.iter_mut() {
+		log.push("input".to_string());
+	}
+}
+
+
+#[test]
+fn it_should_trigger_the_events_of_a_schedule_in_order() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_log(Vec::new()));
+
+	systems.run_frame(
+		&mut entities,
+		&mut Update { delta_time_in_s: 0.5 },
+		&mut Render,
+	);
+
+	assert_eq!(
+		vec![
+			"input".to_string(),
+			"update 0.5".to_string(),
+			"render".to_string(),
+		],
+		entities.logs[id]
+	);
+}
+
+#[test]
+fn it_should_apply_the_control_after_each_stage() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	// Schedules apply the control between stages, no matter what.
+	systems.set_apply_control(ApplyControl::Manual);
+
+	let id = systems.control().add(Entity::new().with_log(Vec::new()));
+
+	systems.run_frame(
+		&mut entities,
+		&mut Update { delta_time_in_s: 0.5 },
+		&mut Render,
+	);
+
+	// The entity is added after the first stage, so it misses input.
+	assert_eq!(
+		vec![
+			"update 0.5".to_string(),
+			"render".to_string(),
+		],
+		entities.logs[id]
+	);
+}
@@ -0,0 +1,87 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	ApplyControl,
+	Components,
+	Control,
+	EntityContainer,
+};
+
+
+world! {
+	components Spawner, Minion;
+
+	events Update();
+
+	system spawn on(Update) with(Spawner, control);
+	system count on(Update) with(Minion, Spawner);
+}
+
+
+pub type Spawner = uint;
+pub type Minion = bool;
+
+
+fn spawn(spawners: &mut Components<Spawner>, control: &mut Control<Entity>) {
+	for (_, _) in spawners.iter() {
+		control.add(Entity::new().with_minion(true));
+	}
+}
+
+fn count(minions: &mut Components<Minion>, spawners: &mut Components<Spawner>) {
+	for (_, seen) in spawners.iter_mut() {
+		*seen = minions.len();
+	}
+}
+
+
+#[test]
+fn it_should_apply_the_control_after_trigger() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_spawner(0));
+
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(1, entities.minions.len());
+	assert_eq!(0, entities.spawners[id]);
+}
+
+#[test]
+fn it_should_apply_the_control_after_each_system() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	systems.set_apply_control(ApplyControl::AfterSystem);
+
+	let id = entities.add(Entity::new().with_spawner(0));
+
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(1, entities.minions.len());
+	assert_eq!(1, entities.spawners[id]);
+}
+
+#[test]
+fn it_should_apply_the_control_only_when_asked_to() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	systems.set_apply_control(ApplyControl::Manual);
+
+	entities.add(Entity::new().with_spawner(0));
+
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(0, entities.minions.len());
+
+	systems.apply_control(&mut entities);
+
+	assert_eq!(1, entities.minions.len());
+}
@@ -0,0 +1,65 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Log;
+
+	events Update();
+
+	system render  on(Update) with(Log);
+	system physics on(Update) with(Log) after(input) before(render);
+	system input   on(Update) with(Log);
+	system audio   on(Update) with(Log) before(input);
+}
+
+
+pub type Log = Vec<&'static str>;
+
+
+fn log(logs: &mut Components<Log>, name: &'static str) {
+	for (_, log) in logs.iter_mut() {
+		log.push(name);
+	}
+}
+
+fn render(logs: &mut Components<Log>) {
+	log(logs, "render");
+}
+
+fn physics(logs: &mut Components<Log>) {
+	log(logs, "physics");
+}
+
+fn input(logs: &mut Components<Log>) {
+	log(logs, "input");
+}
+
+fn audio(logs: &mut Components<Log>) {
+	log(logs, "audio");
+}
+
+
+#[test]
+fn it_should_call_systems_in_the_declared_order() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(Entity::new().with_log(Vec::new()));
+
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(
+		vec!["audio", "input", "physics", "render"],
+		entities.logs[id]
+	);
+}
@@ -0,0 +1,50 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	Components,
+	EntityContainer,
+};
+
+
+world! {
+	components Position;
+
+	events Update();
+
+	system track on(Update) with(Position) state(History);
+}
+
+
+pub type Position = i32;
+
+#[deriving(Default)]
+pub struct History {
+	pub previous: Vec<Position>,
+}
+
+
+fn track(positions: &mut Components<Position>, history: &mut History) {
+	for (_, position) in positions.iter_mut() {
+		history.previous.push(*position);
+		*position += 1;
+	}
+}
+
+
+#[test]
+fn it_should_keep_system_state_between_triggers() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	entities.add(Entity::new().with_position(0));
+
+	systems.trigger_update(&mut entities);
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(vec![0, 1], systems.track_state.previous);
+}
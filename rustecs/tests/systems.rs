@@ -52,7 +52,7 @@ fn update(
 #[test]
 fn it_should_trigger_systems_by_event() {
 	let mut entities = Entities::new();
-	let     systems  = Systems::new();
+	let mut systems  = Systems::new();
 
 	let id = entities.add(
 		Entity::new()
@@ -66,3 +66,20 @@ fn it_should_trigger_systems_by_event() {
 	assert_eq!(false, entities.alphas[id]);
 	assert_eq!(true , entities.betas[id]);
 }
+
+#[test]
+fn it_should_trigger_systems_by_event_specific_method() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let id = entities.add(
+		Entity::new()
+			.with_alpha(false)
+			.with_beta(false)
+	);
+
+	systems.trigger_init(Init, &mut entities);
+
+	assert_eq!(true , entities.alphas[id]);
+	assert_eq!(false, entities.betas[id]);
+}
@@ -0,0 +1,87 @@
+#![feature(phase)]
+
+
+extern crate rustecs;
+#[phase(plugin)] extern crate rustecs_macros;
+
+
+use rustecs::{
+	EntityContainer,
+	EntityId,
+};
+
+
+world! {
+	components Position, Velocity;
+
+	events Push(distance: i32), Update();
+
+	system push      on(Push)   for_components(Position);
+	system integrate on(Update) for_components(Position, &Velocity);
+}
+
+
+pub type Position = i32;
+pub type Velocity = i32;
+
+
+fn push(distance: &i32, _: EntityId, position: &mut Position) {
+	*position += *distance;
+}
+
+fn integrate(_: EntityId, position: &mut Position, velocity: &Velocity) {
+	*position += *velocity;
+}
+
+
+#[test]
+fn it_should_call_per_entity_systems_for_targets_only() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let a = entities.add(Entity::new().with_position(0));
+	let b = entities.add(Entity::new().with_position(0));
+	let c = entities.add(Entity::new().with_velocity(1));
+
+	systems.trigger_for(
+		OwnedEvent::Push(Push { distance: 2 }),
+		&[a, c],
+		&mut entities,
+	);
+
+	assert_eq!(2, entities.positions[a]);
+	assert_eq!(0, entities.positions[b]);
+	assert!(!entities.positions.contains_key(&c));
+}
+
+#[test]
+fn it_should_call_per_entity_systems_once_per_target() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let a = entities.add(Entity::new().with_position(0));
+
+	systems.trigger_for(
+		OwnedEvent::Push(Push { distance: 2 }),
+		&[a, a],
+		&mut entities,
+	);
+
+	assert_eq!(2, entities.positions[a]);
+}
+
+#[test]
+fn it_should_call_per_entity_systems_for_all_matching_entities() {
+	let mut entities = Entities::new();
+	let mut systems  = Systems::new();
+
+	let a = entities.add(Entity::new().with_position(0).with_velocity(1));
+	let b = entities.add(Entity::new().with_position(0).with_velocity(2));
+	let c = entities.add(Entity::new().with_position(0));
+
+	systems.trigger_update(&mut entities);
+
+	assert_eq!(1, entities.positions[a]);
+	assert_eq!(2, entities.positions[b]);
+	assert_eq!(0, entities.positions[c]);
+}
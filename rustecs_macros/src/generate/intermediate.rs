@@ -2,40 +2,58 @@ use syntax::ast;
 use syntax::ext::base::ExtCtxt;
 use syntax::ext::build::AstBuilder;
 use syntax::parse::token;
+use syntax::ptr::P;
 
 use names::{
 	camel_to_snake_case,
+	snake_to_camel_case,
 	type_to_collection_name,
 };
 use parse;
 
 use super::{
 	Components,
+	Events,
+	Resources,
 	Tokens,
 };
 
 
 #[deriving(Clone, Show)]
 pub struct Component {
-	pub name    : String,
-	pub var_name: ast::Ident,
+	pub name      : String,
+	pub var_name  : ast::Ident,
+	pub collection: ast::Ident,
 
-	pub insert: Tokens,
-	pub remove: Tokens,
+	pub insert : Tokens,
+	pub added  : Tokens,
+	pub remove : Tokens,
+	pub reserve: Tokens,
+	pub shrink : Tokens,
+	pub clear  : Tokens,
+	pub methods: Tokens,
 
-	pub field_decl: Tokens,
-	pub field_init: Tokens,
-	pub field_set : Tokens,
+	pub field_decl : Tokens,
+	pub field_init : Tokens,
+	pub field_set  : Tokens,
+	pub field_clone: Tokens,
 
-	pub collection_decl: Tokens,
-	pub collection_init: Tokens,
-	pub collection_arg : Tokens,
+	pub collection_decl    : Tokens,
+	pub collection_init    : Tokens,
+	pub collection_capacity: Tokens,
+	pub collection_arg     : Tokens,
+	pub collection_ref     : Tokens,
 
-	pub builder_fn: Tokens,
+	pub builder_name: ast::Ident,
+	pub builder_fn  : Tokens,
 }
 
 impl Component {
-	pub fn generate(context: &ExtCtxt, path: &ast::Path) -> Component {
+	pub fn generate(
+		context: &ExtCtxt,
+		path   : &ast::Path,
+		hooks  : &[parse::Hook],
+	) -> Component {
 		let ident = path.segments.last().unwrap().identifier;
 		let ty = context.ty_path(path.clone());
 
@@ -54,17 +72,158 @@ impl Component {
 			))
 		};
 
-		let insert = quote_tokens!(context,
-			match entity.$var_name {
-				Some(component) => {
-					let _ = world.$collection.insert(id, component);
-				},
-				None =>
-					()
-			}
+		// Hooks are called after the component has been added or removed. When
+		// an entity is added, the add hooks are only called after all of its
+		// components are in place.
+		let on_add    = hook_functions(hooks, parse::HookKind::Add, ident);
+		let on_remove = hook_functions(hooks, parse::HookKind::Remove, ident);
+		let hooked    = !on_add.is_empty() || !on_remove.is_empty();
+
+		let added_flag = {
+			let mut added_flag = "_added_".to_string();
+			added_flag.push_str(var_name.as_str());
+
+			ast::Ident::new(token::intern(added_flag.as_slice()))
+		};
+		let insert_name = {
+			let mut insert_name = "insert_".to_string();
+			insert_name.push_str(var_name.as_str());
+
+			ast::Ident::new(token::intern(insert_name.as_slice()))
+		};
+		let remove_name = {
+			let mut remove_name = "remove_".to_string();
+			remove_name.push_str(var_name.as_str());
+
+			ast::Ident::new(token::intern(remove_name.as_slice()))
+		};
+
+		let add_hooks = hook_calls(context, on_add.as_slice(),
+			quote_tokens!(context, id, world)
+		);
+		let replace_hooks = hook_calls(context, on_remove.as_slice(),
+			quote_tokens!(context, id, &replaced, world)
+		);
+		let remove_hooks = hook_calls(context, on_remove.as_slice(),
+			quote_tokens!(context, id, &component, self)
+		);
+		let ref_hooks = hook_calls(context, on_remove.as_slice(),
+			quote_tokens!(context, id, component, self)
+		);
+
+		let (insert, added) = if hooked {
+			(
+				quote_tokens!(context,
+					let $added_flag = match entity.$var_name {
+						Some(component) => {
+							match world.$collection.insert(id, component) {
+								Some(replaced) => {
+									$replace_hooks
+								},
+								None =>
+									(),
+							}
+							true
+						},
+						None =>
+							false,
+					};
+				),
+				quote_tokens!(context,
+					if $added_flag {
+						$add_hooks
+					}
+				),
+			)
+		}
+		else {
+			(
+				quote_tokens!(context,
+					match entity.$var_name {
+						Some(component) => {
+							let _ = world.$collection.insert(id, component);
+						},
+						None =>
+							()
+					}
+				),
+				Vec::new(),
+			)
+		};
+		let remove = if on_remove.is_empty() {
+			quote_tokens!(context,
+				self.$collection.remove(&id);
+			)
+		}
+		else {
+			quote_tokens!(context,
+				match self.$collection.remove(&id) {
+					Some(component) => {
+						$remove_hooks
+					},
+					None =>
+						(),
+				}
+			)
+		};
+		let reserve = quote_tokens!(context,
+			self.$collection.reserve(additional);
+		);
+		let shrink = quote_tokens!(context,
+			self.$collection.shrink_to_fit();
 		);
-		let remove = quote_tokens!(context,
-			self.$collection.remove(&id);
+		let clear = if on_remove.is_empty() {
+			quote_tokens!(context,
+				self.$collection.clear();
+			)
+		}
+		else {
+			quote_tokens!(context,
+				let removed: Vec<(_r::rustecs::EntityId, $ty)> =
+					self.$collection.drain().collect();
+				for &(id, ref component) in removed.iter() {
+					$ref_hooks
+				}
+			)
+		};
+
+		let methods = quote_tokens!(context,
+			/// Adds the component to an existing entity, replacing the one it
+			/// might already have. Returns false, if there's no such entity.
+			pub fn $insert_name(&mut self, id: _r::rustecs::EntityId, component: $ty)
+				-> bool
+			{
+				if !self.entities.contains(&id) {
+					return false;
+				}
+
+				let world = self;
+				match world.$collection.insert(id, component) {
+					Some(replaced) => {
+						$replace_hooks
+					},
+					None =>
+						(),
+				}
+				$add_hooks
+
+				true
+			}
+
+			pub fn $remove_name(&mut self, id: _r::rustecs::EntityId)
+				-> Option<$ty>
+			{
+				let removed = self.$collection.remove(&id);
+				match removed {
+					Some(ref component) => {
+						$ref_hooks
+					},
+					None =>
+						(),
+				}
+
+				removed
+			}
 		);
 
         /* Todo: Parenthesis after $foo: are currently required in quote_tokens! to work around
@@ -80,6 +239,9 @@ impl Component {
 		let field_set = quote_tokens!(context,
 			$var_name: (self.$collection.pop(id)),
 		);
+		let field_clone = quote_tokens!(context,
+			$var_name: (self.$collection.get(&id).map(|c| c.clone())),
+		);
 
 		let collection_decl = quote_tokens!(context,
 			pub $collection: (_r::rustecs::Components<$ty>),
@@ -87,8 +249,14 @@ impl Component {
 		let collection_init = quote_tokens!(context,
 			$collection: (_r::rustecs::components()),
 		);
+		let collection_capacity = quote_tokens!(context,
+			$collection: (_r::rustecs::components_with_capacity(capacity)),
+		);
 		let collection_arg = quote_tokens!(context,
-			&mut _entities.$collection,
+			&mut _entities.$collection
+		);
+		let collection_ref = quote_tokens!(context,
+			&_entities.$collection
 		);
 
 		let builder_fn = quote_tokens!(context,
@@ -99,42 +267,557 @@ impl Component {
 		);
 
 		Component {
-			name    : token::get_ident(ident).to_string(),
-			var_name: var_name,
+			name      : token::get_ident(ident).to_string(),
+			var_name  : var_name,
+			collection: collection,
 
-			insert: insert,
-			remove: remove,
+			insert : insert,
+			added  : added,
+			remove : remove,
+			reserve: reserve,
+			shrink : shrink,
+			clear  : clear,
+			methods: methods,
 
-			field_decl: field_decl,
-			field_init: field_init,
-			field_set : field_set,
+			field_decl : field_decl,
+			field_init : field_init,
+			field_set  : field_set,
+			field_clone: field_clone,
 
-			collection_decl: collection_decl,
-			collection_init: collection_init,
-			collection_arg : collection_arg,
+			collection_decl    : collection_decl,
+			collection_init    : collection_init,
+			collection_capacity: collection_capacity,
+			collection_arg     : collection_arg,
+			collection_ref     : collection_ref,
 
-			builder_fn: builder_fn,
+			builder_name: builder_name,
+			builder_fn  : builder_fn,
+		}
+	}
+}
+
+
+fn hook_functions(
+	hooks    : &[parse::Hook],
+	kind     : parse::HookKind,
+	component: ast::Ident,
+) -> Vec<ast::Ident> {
+	hooks
+		.iter()
+		.filter(|hook| hook.kind == kind && hook.component == component)
+		.map(|hook| hook.function)
+		.collect()
+}
+
+fn hook_calls(context: &ExtCtxt, functions: &[ast::Ident], args: Tokens)
+	-> Tokens
+{
+	let mut tokens = Vec::new();
+
+	for function in functions.iter() {
+		tokens.push_all(
+			quote_tokens!(context,
+				$function($args);
+			)
+			.as_slice()
+		);
+	}
+
+	tokens
+}
+
+
+#[deriving(Clone, Show)]
+pub struct Resource {
+	pub name: String,
+
+	pub field_decl: Tokens,
+	pub field_init: Tokens,
+	pub arg       : Tokens,
+	pub arg_ref   : Tokens,
+}
+
+impl Resource {
+	pub fn generate(context: &ExtCtxt, path: &ast::Path) -> Resource {
+		let ident = path.segments.last().unwrap().identifier;
+		let ty = context.ty_path(path.clone());
+
+		let var_name = ast::Ident::new(
+			token::intern(camel_to_snake_case(ident).as_slice())
+		);
+
+		let field_decl = quote_tokens!(context,
+			pub $var_name: ($ty),
+		);
+		let field_init = quote_tokens!(context,
+			$var_name: (::std::default::Default::default()),
+		);
+		let arg = quote_tokens!(context,
+			&mut _entities.$var_name
+		);
+		let arg_ref = quote_tokens!(context,
+			&_entities.$var_name
+		);
+
+		Resource {
+			name: token::get_ident(ident).to_string(),
+
+			field_decl: field_decl,
+			field_init: field_init,
+			arg       : arg,
+			arg_ref   : arg_ref,
 		}
 	}
 }
 
 
 pub struct Event {
-	pub name: ast::Ident,
+	pub name      : ast::Ident,
+	pub params    : Option<Vec<(ast::Ident, P<ast::Ty>)>>,
+	pub decl      : Option<P<ast::Item>>,
+	pub args      : Vec<Tokens>,
+	pub borrowed  : bool,
+	pub trigger_fn: Tokens,
 }
 
 impl Event {
-	pub fn generate(event: ast::Ident) -> Event {
+	pub fn generate(
+		context : &ExtCtxt,
+		event   : &parse::Event,
+		deriving: &Tokens,
+	) -> Event {
+		let name = event.name;
+
+		// Events that are declared with parameters get a generated struct.
+		// Systems receive references to its fields instead of the struct
+		// itself.
+		let (decl, args) = match event.params {
+			Some(ref params) if params.len() == 0 => {
+				let decl = quote_item!(context,
+					$deriving
+					pub struct $name;
+				);
+
+				(decl, Vec::new())
+			},
+
+			Some(ref params) => {
+				let mut fields = Vec::new();
+				let mut args   = Vec::new();
+
+				for &(param, ref ty) in params.iter() {
+					fields.push_all(
+						quote_tokens!(context,
+							pub $param: ($ty),
+						)
+						.as_slice()
+					);
+					args.push(
+						quote_tokens!(context,
+							&_event.$param
+						)
+					);
+				}
+
+				let decl = quote_item!(context,
+					$deriving
+					pub struct $name {
+						$fields
+					}
+				);
+
+				(decl, args)
+			},
+
+			None =>
+				(None, vec![quote_tokens!(context, &mut *_event)]),
+		};
+
+		let trigger_fn = Event::trigger_fn(context, event);
+
 		Event {
-			name: event,
+			name      : name,
+			params    : event.params.clone(),
+			decl      : decl,
+			args      : args,
+			borrowed  : event.params.is_none(),
+			trigger_fn: trigger_fn,
+		}
+	}
+
+	// Generates a method on Systems that constructs the event from its
+	// parameters and triggers it. Events that aren't declared with parameters
+	// are passed in by value.
+	fn trigger_fn(context: &ExtCtxt, event: &parse::Event) -> Tokens {
+		let name         = event.name;
+		let trigger_name = {
+			let mut trigger_name = "trigger_".to_string();
+			trigger_name.push_str(camel_to_snake_case(name).as_slice());
+
+			ast::Ident::new(token::intern(trigger_name.as_slice()))
+		};
+
+		let (params, construct) = match event.params {
+			Some(ref params) if params.len() == 0 =>
+				(Vec::new(), quote_tokens!(context, $name)),
+
+			Some(ref params) => {
+				let mut decls = Vec::new();
+				let mut inits = Vec::new();
+
+				for &(param, ref ty) in params.iter() {
+					decls.push_all(
+						quote_tokens!(context,
+							$param: ($ty),
+						)
+						.as_slice()
+					);
+					inits.push_all(
+						quote_tokens!(context,
+							$param: ($param),
+						)
+						.as_slice()
+					);
+				}
+
+				(decls, quote_tokens!(context, $name { $inits }))
+			},
+
+			None =>
+				(quote_tokens!(context, event: $name,), quote_tokens!(context, event)),
+		};
+
+		quote_tokens!(context,
+			pub fn $trigger_name(&mut self, $params entities: &mut Entities)
+				-> TriggerResult
+			{
+				let mut event = $construct;
+				self.trigger(Event::$name(&mut event), entities)
+			}
+		)
+	}
+}
+
+
+pub struct Prefab {
+	pub name       : ast::Ident,
+	pub constructor: ast::Ident,
+	pub spawner    : ast::Ident,
+	pub entity     : Tokens,
+}
+
+impl Prefab {
+	pub fn generate(
+		context   : &ExtCtxt,
+		prefab    : &parse::Prefab,
+		components: &Components,
+	) -> Prefab {
+		let constructor = ast::Ident::new(token::intern(
+			camel_to_snake_case(prefab.name).as_slice()
+		));
+		let spawner = {
+			let mut spawner = "spawn_".to_string();
+			spawner.push_str(constructor.as_str());
+
+			ast::Ident::new(token::intern(spawner.as_slice()))
+		};
+
+		let mut builder_calls = Vec::new();
+		for &(ident, ref value) in prefab.components.iter() {
+			let component = match components.get(ident.as_str()) {
+				Some(component) =>
+					component,
+				None =>
+					context.span_fatal(
+						value.span,
+						format!(
+							"Prefab {} uses unknown component {}",
+							prefab.name.as_str(),
+							ident.as_str(),
+						)
+						.as_slice()
+					),
+			};
+
+			let builder_name = component.builder_name;
+			let value        = Prefab::value(context, value);
+
+			builder_calls.push_all(
+				quote_tokens!(context,
+					.$builder_name($value)
+				)
+				.as_slice()
+			);
+		}
+
+		let entity = quote_tokens!(context,
+			Entity::new()$builder_calls
+		);
+
+		Prefab {
+			name       : prefab.name,
+			constructor: constructor,
+			spawner    : spawner,
+			entity     : entity,
+		}
+	}
+
+	// `Default` is shorthand for the component's default value.
+	fn value(context: &ExtCtxt, value: &P<ast::Expr>) -> Tokens {
+		match value.node {
+			ast::ExprPath(ref path)
+				if path.segments.len() == 1
+				&& path.segments[0].identifier.as_str() == "Default" =>
+					quote_tokens!(context,
+						::std::default::Default::default()
+					),
+
+			_ =>
+				quote_tokens!(context,
+					$value
+				),
+		}
+	}
+}
+
+
+pub struct Schedule {
+	pub run_fn: Tokens,
+}
+
+impl Schedule {
+	pub fn generate(
+		context : &ExtCtxt,
+		schedule: &parse::Schedule,
+		events  : &Events,
+		fallible: bool,
+	) -> Schedule {
+		let run_name = {
+			let mut run_name = "run_".to_string();
+			run_name.push_str(camel_to_snake_case(schedule.name).as_slice());
+
+			ast::Ident::new(token::intern(run_name.as_slice()))
+		};
+
+		let mut params = Vec::new();
+		let mut stages = Vec::new();
+
+		for &stage in schedule.stages.iter() {
+			let event = match events.iter().find(|event| event.name == stage) {
+				Some(event) =>
+					event,
+				None =>
+					context.span_fatal(
+						schedule.span,
+						format!(
+							"Schedule {} contains unknown event {}",
+							schedule.name.as_str(),
+							stage.as_str(),
+						)
+						.as_slice()
+					),
+			};
+
+			// Events without parameters are constructed on the spot, all
+			// others need to be passed in.
+			let event_arg = match event.params {
+				Some(ref params) if params.len() == 0 =>
+					quote_tokens!(context, &mut $stage),
+
+				_ => {
+					let param = ast::Ident::new(token::intern(
+						camel_to_snake_case(stage).as_slice()
+					));
+
+					params.push_all(
+						quote_tokens!(context,
+							$param: (&mut $stage),
+						)
+						.as_slice()
+					);
+
+					quote_tokens!(context, $param)
+				},
+			};
+
+			stages.push_all(
+				trigger_stage(context, stage, event_arg, fallible).as_slice()
+			);
+		}
+
+		let result = stages_result(context, fallible);
+
+		let run_fn = quote_tokens!(context,
+			pub fn $run_name(&mut self, entities: &mut Entities, $params)
+				-> TriggerResult
+			{
+				$stages
+				$result
+			}
+		);
+
+		Schedule {
+			run_fn: run_fn,
 		}
 	}
 }
 
 
+pub struct FixedTimestep {
+	pub run_fn: Tokens,
+}
+
+impl FixedTimestep {
+	pub fn generate(
+		context : &ExtCtxt,
+		timestep: &parse::FixedTimestep,
+		events  : &Events,
+		fallible: bool,
+	) -> FixedTimestep {
+		let update = FixedTimestep::event_arg(
+			context,
+			timestep,
+			events,
+			timestep.update,
+			quote_tokens!(context, frame.step_in_s),
+		);
+		let render = FixedTimestep::event_arg(
+			context,
+			timestep,
+			events,
+			timestep.render,
+			quote_tokens!(context, frame.alpha),
+		);
+
+		let update_stage = trigger_stage(context, timestep.update, update, fallible);
+		let render_stage = trigger_stage(context, timestep.render, render, fallible);
+		let result       = stages_result(context, fallible);
+
+		let run_fn = quote_tokens!(context,
+			/// Triggers the update event once for every step that is due, and
+			/// the render event once with the interpolation alpha. The control
+			/// is applied after each of them.
+			pub fn run_fixed_timestep<C: _r::rustecs::Clock>(
+				&mut self,
+				timestep: &mut _r::rustecs::FixedTimestep<C>,
+				entities: &mut Entities,
+			) -> TriggerResult {
+				let frame = timestep.advance();
+
+				for _ in range(0, frame.steps) {
+					$update_stage
+				}
+				$render_stage
+
+				$result
+			}
+		);
+
+		FixedTimestep {
+			run_fn: run_fn,
+		}
+	}
+
+	// The events can have a single parameter, which receives the duration of
+	// a step or the interpolation alpha, respectively.
+	fn event_arg(
+		context : &ExtCtxt,
+		timestep: &parse::FixedTimestep,
+		events  : &Events,
+		name    : ast::Ident,
+		value   : Tokens,
+	) -> Tokens {
+		let event = match events.iter().find(|event| event.name == name) {
+			Some(event) =>
+				event,
+			None =>
+				context.span_fatal(
+					timestep.span,
+					format!(
+						"Fixed timestep uses unknown event {}",
+						name.as_str(),
+					)
+					.as_slice()
+				),
+		};
+
+		match event.params {
+			Some(ref params) if params.len() == 0 =>
+				quote_tokens!(context, &mut $name),
+
+			Some(ref params) if params.len() == 1 => {
+				let (param, _) = params[0];
+				quote_tokens!(context, &mut $name { $param: ($value) })
+			},
+
+			_ =>
+				context.span_fatal(
+					timestep.span,
+					format!(
+						"Event {} of the fixed timestep needs a parameter list with at most one parameter",
+						name.as_str(),
+					)
+					.as_slice()
+				),
+		}
+	}
+}
+
+
+// Triggers an event and applies the control afterwards. If systems can fail,
+// the stages that follow are skipped, if this one failed.
+fn trigger_stage(
+	context  : &ExtCtxt,
+	event    : ast::Ident,
+	event_arg: Tokens,
+	fallible : bool,
+) -> Tokens {
+	if fallible {
+		quote_tokens!(context,
+			let result = self.trigger(Event::$event($event_arg), entities);
+			self.control.apply(entities);
+			try!(result);
+		)
+	}
+	else {
+		quote_tokens!(context,
+			self.trigger(Event::$event($event_arg), entities);
+			self.control.apply(entities);
+		)
+	}
+}
+
+fn stages_result(context: &ExtCtxt, fallible: bool) -> Tokens {
+	if fallible {
+		quote_tokens!(context, Ok(()))
+	}
+	else {
+		quote_tokens!(context, ())
+	}
+}
+
+
 pub struct System {
-	pub event: ast::Ident,
-	pub call : Tokens,
+	pub name       : ast::Ident,
+	pub variant    : ast::Ident,
+	pub before     : Vec<ast::Ident>,
+	pub after      : Vec<ast::Ident>,
+	pub calls      : Vec<SystemCall>,
+	pub conditional: bool,
+	pub per_entity : bool,
+	pub fallible   : bool,
+	pub reads      : Vec<String>,
+	pub writes     : Vec<String>,
+	pub state_decl : Tokens,
+	pub state_init : Tokens,
+}
+
+/// How a system is called for one of the events it's listening to.
+pub struct SystemCall {
+	pub event    : ast::Ident,
+	pub args     : Vec<Tokens>,
+	pub condition: Tokens,
+	pub call     : Tokens,
 }
 
 impl System {
@@ -142,40 +825,468 @@ impl System {
 		context   : &ExtCtxt,
 		system    : &parse::System,
 		components: &Components,
+		resources : &Resources,
+		events    : &Events,
+		fallible  : bool,
 	) -> System {
 		let name = system.name;
 
-		let component_args =
-			System::component_args(context, system, components);
+		let system_events: Vec<&Event> = system.events
+			.iter()
+			.map(|&name| {
+				match events.iter().find(|event| event.name == name) {
+					Some(event) =>
+						event,
+					None =>
+						context.span_fatal(
+							system.span,
+							format!(
+								"System {} is triggered by unknown event {}",
+								system.name.as_str(),
+								name.as_str(),
+							)
+							.as_slice()
+						),
+				}
+			})
+			.collect();
+
+		// Systems that listen to more than one event receive the event enum,
+		// instead of the event's parameters.
+		let generic = system_events.len() > 1;
+
+		let mut args   = Vec::new();
+		let mut reads  = Vec::new();
+		let mut writes = Vec::new();
 
-		let call = quote_tokens!(context,
-			$name(_event, $component_args);
+		// Events without parameters are passed by mutable reference, so
+		// systems that receive them can't run at the same time. The
+		// parameters of all other events are only read.
+		if generic || system_events.iter().any(|event| event.borrowed) {
+			writes.push("event".to_string());
+		}
+		else if system_events.iter().any(|event| !event.args.is_empty()) {
+			reads.push("event".to_string());
+		}
+
+		// Systems that are declared with `for_components` are called once for
+		// each entity that has all of those components. They receive the id
+		// and the entity's components, after the event arguments.
+		let per_entity = !system.components.is_empty();
+		let has_components = System::entity_args(
+			context,
+			system,
+			components,
+			&mut args,
+			&mut reads,
+			&mut writes,
+		);
+
+		System::args(
+			context,
+			system,
+			components,
+			resources,
+			&mut args,
+			&mut reads,
+			&mut writes,
 		);
 
+		// Systems that declare state get their own instance of it, which is
+		// passed as the last argument.
+		let (state_decl, state_init) = match system.state {
+			Some(ref ty) => {
+				let state = {
+					let mut state = name.as_str().to_string();
+					state.push_str("_state");
+
+					ast::Ident::new(token::intern(state.as_slice()))
+				};
+
+				args.push(quote_tokens!(context, &mut self.$state));
+
+				(
+					quote_tokens!(context,
+						pub $state: ($ty),
+					),
+					quote_tokens!(context,
+						$state: (::std::default::Default::default()),
+					),
+				)
+			},
+
+			None =>
+				(Vec::new(), Vec::new()),
+		};
+
+		let variant = ast::Ident::new(token::intern(
+			snake_to_camel_case(name).as_slice()
+		));
+
+		let calls = system_events
+			.iter()
+			.map(|event|
+				System::call(
+					context,
+					system,
+					*event,
+					generic,
+					variant,
+					args.as_slice(),
+					&has_components,
+					fallible,
+				)
+			)
+			.collect();
+
 		System {
-			event: system.event,
-			call : call,
+			name       : name,
+			variant    : variant,
+			before     : system.before.clone(),
+			after      : system.after.clone(),
+			calls      : calls,
+			conditional: !system.conditions.is_empty(),
+			per_entity : per_entity,
+			fallible   : fallible,
+			reads      : reads,
+			writes     : writes,
+			state_decl : state_decl,
+			state_init : state_init,
 		}
 	}
 
-	fn component_args(
+	pub fn call_for(&self, event: ast::Ident) -> Option<&SystemCall> {
+		self.calls.iter().find(|call| call.event == event)
+	}
+
+	fn call(
+		context       : &ExtCtxt,
+		system        : &parse::System,
+		event         : &Event,
+		generic       : bool,
+		variant       : ast::Ident,
+		system_args   : &[Tokens],
+		has_components: &Tokens,
+		fallible      : bool,
+	) -> SystemCall {
+		let name        = system.name;
+		let event_ident = event.name;
+
+		let (mut args, event_ref) = if generic {
+			(
+				vec![quote_tokens!(context, &mut Event::$event_ident(&mut *_event))],
+				quote_tokens!(context, &Event::$event_ident(&mut *_event)),
+			)
+		}
+		else {
+			(event.args.clone(), quote_tokens!(context, &*_event))
+		};
+		args.push_all(system_args);
+
+		// The system is only called, if it's enabled and all of its conditions
+		// hold. Conditions are functions that receive the event and the
+		// entities.
+		let mut condition = quote_tokens!(context,
+			self.enabled[SystemName::$variant.index()]
+		);
+		for predicate in system.conditions.iter() {
+			condition.push_all(
+				quote_tokens!(context,
+					&& $predicate($event_ref, &*_entities)
+				)
+				.as_slice()
+			);
+		}
+
+		let mut arg_list = Vec::new();
+		for arg in args.iter() {
+			arg_list.push_all(arg.as_slice());
+			arg_list.push_all(quote_tokens!(context, ,).as_slice());
+		}
+
+		// Every call is reported to the profiler, if the profiling feature is
+		// enabled. Otherwise no instrumentation is generated at all.
+		let system_name = name.as_str();
+		let event_name  = event.name.as_str();
+		let (start, end) = if cfg!(feature = "profiling") {
+			(
+				quote_tokens!(context,
+					self.start_profiling(
+						$system_name,
+						$event_name,
+						_r::rustecs::precise_time_ns(),
+					);
+				),
+				quote_tokens!(context,
+					self.end_profiling(
+						$system_name,
+						$event_name,
+						_r::rustecs::precise_time_ns(),
+					);
+				),
+			)
+		}
+		else {
+			(Vec::new(), Vec::new())
+		};
+
+		// Errors returned by fallible systems are collected. Depending on the
+		// error policy, the remaining systems for the event are skipped.
+		let invocation = quote_tokens!(context, $name($arg_list));
+		let (abort_decl, invoke, stop, abort) = if fallible {
+			(
+				quote_tokens!(context,
+					let mut _abort = false;
+				),
+				quote_tokens!(context,
+					let _result = _r::rustecs::IntoSystemResult::into_system_result(
+						$invocation
+					);
+					if self.handle_result(&mut _errors, $system_name, $event_name, _result) {
+						_abort = true;
+					}
+				),
+				quote_tokens!(context,
+					if _abort {
+						break;
+					}
+				),
+				quote_tokens!(context,
+					if _abort {
+						break 'systems;
+					}
+				),
+			)
+		}
+		else {
+			(
+				Vec::new(),
+				quote_tokens!(context,
+					$invocation;
+				),
+				Vec::new(),
+				Vec::new(),
+			)
+		};
+
+		// Without targets, the ids are borrowed straight from the entities, so
+		// they don't need to be copied for every call.
+		let call = if !system.components.is_empty() {
+			quote_tokens!(context,
+				if $condition {
+					$start
+					$abort_decl
+
+					match _targets {
+						Some(targets) =>
+							for &_id in targets.iter() {
+								if $has_components {
+									$invoke
+									$stop
+								}
+							},
+						None =>
+							for &_id in _entities.entities.iter() {
+								if $has_components {
+									$invoke
+									$stop
+								}
+							},
+					}
+
+					$end
+
+					if self.apply_control == _r::rustecs::ApplyControl::AfterSystem {
+						self.control.apply(_entities);
+					}
+
+					$abort
+				}
+			)
+		}
+		else {
+			quote_tokens!(context,
+				if $condition {
+					$start
+					$abort_decl
+					$invoke
+					$end
+
+					if self.apply_control == _r::rustecs::ApplyControl::AfterSystem {
+						self.control.apply(_entities);
+					}
+
+					$abort
+				}
+			)
+		};
+
+		SystemCall {
+			event    : event.name,
+			args     : args,
+			condition: condition,
+			call     : call,
+		}
+	}
+
+	/// Whether the two systems have to run one after the other. That's the
+	/// case, if either writes data the other one accesses, or if their order
+	/// was declared explicitly.
+	pub fn conflicts_with(&self, other: &System) -> bool {
+		// Systems that are called per entity are never run on their own
+		// thread.
+		self.per_entity || other.per_entity
+			|| self.writes.iter().any(|data| other.accesses(data.as_slice()))
+			|| other.writes.iter().any(|data| self.accesses(data.as_slice()))
+			|| self.before.contains(&other.name)
+			|| self.after.contains(&other.name)
+			|| other.before.contains(&self.name)
+			|| other.after.contains(&self.name)
+	}
+
+	fn accesses(&self, data: &str) -> bool {
+		let data = data.to_string();
+
+		// Conditions look at the event and all of the entities, but can't see
+		// the control or the event queue.
+		let inspected = self.conditional
+			&& data.as_slice() != "control"
+			&& data.as_slice() != "events";
+
+		inspected || self.reads.contains(&data) || self.writes.contains(&data)
+	}
+
+	fn entity_args(
 		context   : &ExtCtxt,
 		system    : &parse::System,
 		components: &Components,
+		args      : &mut Vec<Tokens>,
+		reads     : &mut Vec<String>,
+		writes    : &mut Vec<String>,
 	) -> Tokens {
-		let mut tokens = Vec::new();
+		let mut has_components = quote_tokens!(context, true);
+
+		if system.components.is_empty() {
+			return has_components;
+		}
+
+		args.push(quote_tokens!(context, _id));
+
+		for arg in system.components.iter() {
+			let name = arg.name.as_str();
+
+			let component = match components.get(name) {
+				Some(component) =>
+					component,
+				None =>
+					context.span_fatal(
+						system.span,
+						format!(
+							"System {} is called for unknown component {}",
+							system.name.as_str(),
+							name,
+						)
+						.as_slice()
+					),
+			};
+
+			if system.args.iter().any(|other| other.name == arg.name) {
+				context.span_fatal(
+					system.span,
+					format!(
+						"System {} asks for {} per entity and as a collection",
+						system.name.as_str(),
+						name,
+					)
+					.as_slice()
+				);
+			}
 
-		for ident in system.components.iter() {
-			let ref arg = components[ident.as_str().to_string()].collection_arg;
+			let collection = component.collection;
 
-			tokens.push_all(
+			if arg.access == parse::Access::Write {
+				args.push(quote_tokens!(context,
+					_entities.$collection.get_mut(&_id).unwrap()
+				));
+				writes.push(name.to_string());
+			}
+			else {
+				args.push(quote_tokens!(context,
+					&_entities.$collection[_id]
+				));
+				reads.push(name.to_string());
+			}
+
+			has_components.push_all(
 				quote_tokens!(context,
-					$arg
+					&& _entities.$collection.contains_key(&_id)
 				)
 				.as_slice()
 			);
 		}
 
-		tokens
+		has_components
+	}
+
+	fn args(
+		context   : &ExtCtxt,
+		system    : &parse::System,
+		components: &Components,
+		resources : &Resources,
+		args      : &mut Vec<Tokens>,
+		reads     : &mut Vec<String>,
+		writes    : &mut Vec<String>,
+	) {
+		for arg in system.args.iter() {
+			let write = arg.access == parse::Access::Write;
+
+			let tokens = match arg.name.as_str() {
+				"control" | "events" if !write =>
+					context.span_fatal(
+						system.span,
+						format!(
+							"System {} needs mutable access to {}",
+							system.name.as_str(),
+							arg.name.as_str(),
+						)
+						.as_slice()
+					),
+
+				"control" =>
+					quote_tokens!(context, &mut self.control),
+				"events" =>
+					quote_tokens!(context, &mut self.events),
+
+				name => match (components.get(name), resources.get(name)) {
+					(Some(component), _) if write =>
+						component.collection_arg.clone(),
+					(Some(component), _) =>
+						component.collection_ref.clone(),
+					(None, Some(resource)) if write =>
+						resource.arg.clone(),
+					(None, Some(resource)) =>
+						resource.arg_ref.clone(),
+					(None, None) =>
+						context.span_fatal(
+							system.span,
+							format!(
+								"System {} uses unknown component or resource {}",
+								system.name.as_str(),
+								name,
+							)
+							.as_slice()
+						),
+				},
+			};
+
+			if write {
+				writes.push(arg.name.as_str().to_string());
+			}
+			else {
+				reads.push(arg.name.as_str().to_string());
+			}
+
+			args.push(tokens);
+		}
 	}
 }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use syntax::ast;
 use syntax::ext::base::ExtCtxt;
+use syntax::parse::token;
 use syntax::ptr::P;
 
 use parse;
@@ -8,23 +9,33 @@ use parse;
 use self::intermediate::{
 	Component,
 	Event,
+	FixedTimestep,
+	Prefab,
+	Resource,
+	Schedule,
 	System,
 };
 use self::output::{
 	EntitiesGenerator,
 	EntityGenerator,
 	EventGenerator,
+	PrefabGenerator,
 	SystemsGenerator,
 };
 
 
 mod intermediate;
+mod order;
 mod output;
+mod parallel;
 
 
 type Components = HashMap<String, Component>;
+type Resources  = HashMap<String, Resource>;
 type Events     = Vec<Event>;
 type Systems    = Vec<System>;
+type Schedules  = Vec<Schedule>;
+type Prefabs    = Vec<Prefab>;
 
 type Items      = Vec<P<ast::Item>>;
 type Tokens     = Vec<ast::TokenTree>;
@@ -40,73 +51,169 @@ pub fn items(context: &ExtCtxt, world: &parse::World) -> Items {
 		}
 	);
 
-	let mut derived_traits = Vec::new();
-	for (i, ident) in world.derived_traits.iter().enumerate() {
-		if i + 1 == world.derived_traits.len() {
-			derived_traits.push_all(
-				quote_tokens!(context,
-					$ident
-				)
-				.as_slice()
-			);
-		}
-		else {
-			derived_traits.push_all(
-				quote_tokens!(context,
-					$ident,
-				)
-				.as_slice()
-			);
-		}
-	}
+	let deriving = deriving_attribute(context, world.derived_traits.as_slice());
 
-	let deriving = if world.derived_traits.len() > 0 {
-		quote_tokens!(context,
-			#[deriving($derived_traits)]
+	// The borrowed event enum holds mutable references, which can't be cloned
+	// or decoded.
+	let borrowed_traits: Vec<ast::Ident> = world.derived_traits
+		.iter()
+		.filter(|ident|
+			ident.as_str() != "Clone" && ident.as_str() != "Decodable"
 		)
-	}
-	else {
-		Vec::new()
-	};
+		.map(|ident| *ident)
+		.collect();
+	let event_deriving = deriving_attribute(context, borrowed_traits.as_slice());
 
 	let components: Components = world.components
 		.iter()
 		.map(|component|
-			Component::generate(context, component)
+			Component::generate(context, component, world.hooks.as_slice())
 		)
 		.map(|component|
 			(component.name.clone(), component)
 		)
 		.collect();
+	for hook in world.hooks.iter() {
+		if !components.contains_key(hook.component.as_str()) {
+			context.span_fatal(
+				hook.span,
+				format!(
+					"Hook {} is registered for unknown component {}",
+					hook.function.as_str(),
+					hook.component.as_str(),
+				)
+				.as_slice()
+			);
+		}
+	}
+
+	let resources: Resources = world.resources
+		.iter()
+		.map(|resource|
+			Resource::generate(context, resource)
+		)
+		.map(|resource|
+			(resource.name.clone(), resource)
+		)
+		.collect();
 	let events: Events = world.events
 		.iter()
 		.map(|event|
-			Event::generate(*event)
+			Event::generate(context, event, &deriving)
 		)
 		.collect();
-	let systems: Systems = world.systems
-		.iter()
+	// If the world declares an error type, systems may fail.
+	let fallible = world.error.is_some();
+
+	let systems: Systems = order::sort(context, world.systems.as_slice())
+		.into_iter()
 		.map(|system|
-			System::generate(context, system, &components)
+			System::generate(
+				context,
+				system,
+				&components,
+				&resources,
+				&events,
+				fallible,
+			)
+		)
+		.collect();
+	let schedules: Schedules = world.schedules
+		.iter()
+		.map(|schedule|
+			Schedule::generate(context, schedule, &events, fallible)
+		)
+		.collect();
+	let fixed_timestep = world.fixed_timestep
+		.as_ref()
+		.map(|timestep|
+			FixedTimestep::generate(context, timestep, &events, fallible)
+		);
+	let prefabs: Prefabs = world.prefabs
+		.iter()
+		.map(|prefab|
+			Prefab::generate(context, prefab, &components)
 		)
 		.collect();
 
-	let entities = EntitiesGenerator::generate(context, &components, &deriving);
+	// Importing an entity with an id that is already in use replaces the
+	// existing entity, unless the world says otherwise.
+	let import_policy = world.import_policy.unwrap_or_else(||
+		ast::Ident::new(token::intern("Replace"))
+	);
+
+	let cloneable = world.derived_traits
+		.iter()
+		.any(|ident| ident.as_str() == "Clone");
+	let showable = world.derived_traits
+		.iter()
+		.any(|ident| ident.as_str() == "Show");
+
+	let entities = EntitiesGenerator::generate(
+		context,
+		&components,
+		&resources,
+		import_policy,
+		cloneable,
+		&deriving,
+	);
 	let entity   = EntityGenerator::generate(context, &components, &deriving);
-	let event    = EventGenerator::generate(context, &events, &deriving);
+	let prefabs  = PrefabGenerator::generate(context, &prefabs);
+	let event    = EventGenerator::generate(
+		context,
+		&events,
+		cloneable,
+		&deriving,
+		&event_deriving,
+	);
 	let systems  = SystemsGenerator::generate(
 		context,
 		&events,
 		systems,
-		&deriving,
+		&schedules,
+		&fixed_timestep,
+		&world.error,
+		showable,
 	);
 
 	let mut items = Vec::new();
 	items.push_all(vec![extern_crate_rustecs.unwrap()].as_slice());
 	items.push_all(entities.0.as_slice());
 	items.push_all(entity.0.as_slice());
+	items.push_all(prefabs.0.as_slice());
 	items.push_all(event.0.as_slice());
 	items.push_all(systems.0.as_slice());
 
 	items
 }
+
+fn deriving_attribute(context: &ExtCtxt, traits: &[ast::Ident]) -> Tokens {
+	let mut derived_traits = Vec::new();
+	for (i, ident) in traits.iter().enumerate() {
+		if i + 1 == traits.len() {
+			derived_traits.push_all(
+				quote_tokens!(context,
+					$ident
+				)
+				.as_slice()
+			);
+		}
+		else {
+			derived_traits.push_all(
+				quote_tokens!(context,
+					$ident,
+				)
+				.as_slice()
+			);
+		}
+	}
+
+	if traits.len() > 0 {
+		quote_tokens!(context,
+			#[deriving($derived_traits)]
+		)
+	}
+	else {
+		Vec::new()
+	}
+}
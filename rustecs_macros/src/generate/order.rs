@@ -0,0 +1,103 @@
+use syntax::ext::base::ExtCtxt;
+
+use parse;
+
+
+/// Sorts the systems, so every system comes after the systems it is declared
+/// to run after, and before the systems it is declared to run before. Systems
+/// that aren't constrained keep the order they were declared in.
+pub fn sort<'a>(
+	context: &ExtCtxt,
+	systems: &'a [parse::System],
+) -> Vec<&'a parse::System> {
+	// predecessors[i] contains the indices of all systems that need to run
+	// before system i.
+	let mut predecessors: Vec<Vec<uint>> =
+		Vec::from_fn(systems.len(), |_| Vec::new());
+
+	for (i, system) in systems.iter().enumerate() {
+		for name in system.after.iter() {
+			let j = index_of(context, systems, system, name.as_str());
+			predecessors[i].push(j);
+		}
+		for name in system.before.iter() {
+			let j = index_of(context, systems, system, name.as_str());
+			predecessors[j].push(i);
+		}
+	}
+
+	let mut sorted = Vec::with_capacity(systems.len());
+	let mut placed = Vec::from_elem(systems.len(), false);
+
+	while sorted.len() < systems.len() {
+		let next = range(0, systems.len()).find(|&i|
+			!placed[i] && predecessors[i].iter().all(|&j| placed[j])
+		);
+
+		match next {
+			Some(i) => {
+				placed[i] = true;
+				sorted.push(&systems[i]);
+			},
+			None => {
+				// None of the remaining systems can be placed, so each of them
+				// has a predecessor that hasn't been placed either. Following
+				// those predecessors eventually leads back to a system that was
+				// already visited, which closes the cycle. Systems that merely
+				// depend on the cycle aren't part of it.
+				let mut path: Vec<uint> = Vec::new();
+				let mut i = range(0, systems.len())
+					.find(|&i| !placed[i])
+					.unwrap();
+				while !path.contains(&i) {
+					path.push(i);
+					i = *predecessors[i]
+						.iter()
+						.find(|&&j| !placed[j])
+						.unwrap();
+				}
+
+				let start = path.iter().position(|&j| j == i).unwrap();
+				let mut cycle: Vec<&str> = path
+					.slice_from(start)
+					.iter()
+					.map(|&j| systems[j].name.as_str())
+					.collect();
+				cycle.reverse();
+
+				context.span_fatal(
+					systems[i].span,
+					format!(
+						"Ordering constraints of systems {} form a cycle",
+						cycle.connect(", "),
+					)
+					.as_slice()
+				);
+			},
+		}
+	}
+
+	sorted
+}
+
+fn index_of(
+	context: &ExtCtxt,
+	systems: &[parse::System],
+	system : &parse::System,
+	name   : &str,
+) -> uint {
+	match systems.iter().position(|other| other.name.as_str() == name) {
+		Some(i) =>
+			i,
+		None =>
+			context.span_fatal(
+				system.span,
+				format!(
+					"System {} is ordered relative to unknown system {}",
+					system.name.as_str(),
+					name,
+				)
+				.as_slice()
+			),
+	}
+}
@@ -1,28 +1,95 @@
+use syntax::ast;
 use syntax::ext::base::ExtCtxt;
+use syntax::ptr::P;
 
 use super::{
 	Components,
 	Events,
 	Items,
+	Prefabs,
+	Resources,
+	Schedules,
 	Systems,
 	Tokens,
 };
+use super::intermediate::{
+	FixedTimestep,
+	System,
+};
+use super::parallel;
 
 
 pub struct EntitiesGenerator(pub Items);
 
 impl EntitiesGenerator {
 	pub fn generate(
-		context   : &ExtCtxt,
-		components: &Components,
-		deriving  : &Tokens,
+		context      : &ExtCtxt,
+		components   : &Components,
+		resources    : &Resources,
+		import_policy: ast::Ident,
+		cloneable    : bool,
+		deriving     : &Tokens,
 	) -> EntitiesGenerator {
 		let collection_decls = EntitiesGenerator::collection_decls(components);
 		let collection_inits = EntitiesGenerator::collection_inits(components);
+		let resource_decls   = EntitiesGenerator::resource_decls(resources);
+		let resource_inits   = EntitiesGenerator::resource_inits(resources);
+		let capacities       = EntitiesGenerator::capacities(components);
 		let inserts          = EntitiesGenerator::inserts(components);
+		let addeds           = EntitiesGenerator::addeds(components);
+		let methods          = EntitiesGenerator::methods(components);
 		let removes          = EntitiesGenerator::removes(components);
+		let reserves         = EntitiesGenerator::reserves(components);
+		let shrinks          = EntitiesGenerator::shrinks(components);
+		let clears           = EntitiesGenerator::clears(components);
 		let field_sets       = EntitiesGenerator::field_sets(components);
 
+		// Duplicating entities requires all components to implement Clone,
+		// which is only guaranteed if the world derives it.
+		let (duplicate, duplicate_as) = if cloneable {
+			let field_clones = EntitiesGenerator::field_clones(components);
+
+			let duplicate = quote_tokens!(context,
+				pub fn duplicate(&mut self, id: _r::rustecs::EntityId)
+					-> Option<_r::rustecs::EntityId>
+				{
+					let new_id = self.next_id;
+
+					if _r::rustecs::EntityContainer::duplicate_as(self, id, new_id) {
+						self.next_id += 2;
+						Some(new_id)
+					}
+					else {
+						None
+					}
+				}
+			);
+			let duplicate_as = quote_tokens!(context,
+				fn duplicate_as(
+					&mut self,
+					id    : _r::rustecs::EntityId,
+					new_id: _r::rustecs::EntityId,
+				) -> bool {
+					if !self.entities.contains(&id) {
+						return false;
+					}
+
+					let entity = Entity { $field_clones };
+					_r::rustecs::EntityContainer::import_with(
+						self,
+						new_id,
+						entity,
+						_r::rustecs::ImportPolicy::Replace,
+					)
+				}
+			);
+
+			(duplicate, duplicate_as)
+		}
+		else {
+			(Vec::new(), Vec::new())
+		};
+
 		let structure = quote_item!(context,
 			$deriving
 			pub struct Entities {
@@ -30,6 +97,8 @@ impl EntitiesGenerator {
 				next_id : _r::rustecs::EntityId,
 
 				$collection_decls
+
+				$resource_decls
 			}
 		);
 
@@ -40,8 +109,63 @@ impl EntitiesGenerator {
 						entities: ::std::collections::HashSet::new(),
 						next_id : 0,
 						$collection_inits
+						$resource_inits
 					}
 				}
+
+				pub fn with_capacity(capacity: uint) -> Entities {
+					Entities {
+						entities: ::std::collections::HashSet::with_capacity(capacity),
+						next_id : 0,
+						$capacities
+						$resource_inits
+					}
+				}
+
+				pub fn reserve(&mut self, additional: uint) {
+					self.entities.reserve(additional);
+					$reserves
+				}
+
+				pub fn shrink_to_fit(&mut self) {
+					self.entities.shrink_to_fit();
+					$shrinks
+				}
+
+				/// Removes all entities. Ids of removed entities won't be
+				/// handed out again, so ids that are still stored somewhere
+				/// else can't refer to new entities by accident.
+				pub fn clear(&mut self) {
+					self.entities.clear();
+					$clears
+				}
+
+				/// Removes all entities and starts handing out ids from the
+				/// beginning again.
+				pub fn reset(&mut self) {
+					self.clear();
+					self.next_id = 0;
+				}
+
+				pub fn len(&self) -> uint {
+					self.entities.len()
+				}
+
+				pub fn is_empty(&self) -> bool {
+					self.entities.is_empty()
+				}
+
+				pub fn contains(&self, id: _r::rustecs::EntityId) -> bool {
+					self.entities.contains(&id)
+				}
+
+				pub fn ids(&self) -> _r::rustecs::Ids {
+					_r::rustecs::Ids::new(&self.entities)
+				}
+
+				$methods
+
+				$duplicate
 			}
 		);
 
@@ -55,15 +179,45 @@ impl EntitiesGenerator {
 
 					let world = self;
 					$inserts
+					$addeds
 
 					id
 				}
 
-				fn import(&mut self, id: _r::rustecs::EntityId, entity: Entity) {
+				fn import(&mut self, id: _r::rustecs::EntityId, entity: Entity) -> bool {
+					_r::rustecs::EntityContainer::import_with(
+						self,
+						id,
+						entity,
+						_r::rustecs::ImportPolicy::$import_policy,
+					)
+				}
+
+				fn import_with(
+					&mut self,
+					id    : _r::rustecs::EntityId,
+					entity: Entity,
+					policy: _r::rustecs::ImportPolicy,
+				) -> bool {
+					if self.entities.contains(&id) {
+						match policy {
+							_r::rustecs::ImportPolicy::Replace => {
+								$removes
+							},
+							_r::rustecs::ImportPolicy::Merge =>
+								(),
+							_r::rustecs::ImportPolicy::Reject =>
+								return false,
+						}
+					}
+
 					self.entities.insert(id);
 
 					let world = self;
 					$inserts
+					$addeds
+
+					true
 				}
 
 				fn remove(&mut self, id: _r::rustecs::EntityId) {
@@ -72,6 +226,22 @@ impl EntitiesGenerator {
 					$removes
 				}
 
+				$duplicate_as
+
+				fn add_batch<I: Iterator<Entity>>(&mut self, mut entities: I)
+					-> Vec<_r::rustecs::EntityId>
+				{
+					let (additional, _) = entities.size_hint();
+					self.reserve(additional);
+
+					let mut ids = Vec::with_capacity(additional);
+					for entity in entities {
+						ids.push(_r::rustecs::EntityContainer::add(self, entity));
+					}
+
+					ids
+				}
+
 				fn export(mut self) -> Vec<(_r::rustecs::EntityId, Entity)> {
 					let ids: Vec<_r::rustecs::EntityId> = self.entities
 						.iter()
@@ -88,10 +258,19 @@ impl EntitiesGenerator {
 			}
 		);
 
+		let extend_impl = quote_item!(context,
+			impl Extend<Entity> for Entities {
+				fn extend<I: Iterator<Entity>>(&mut self, entities: I) {
+					_r::rustecs::EntityContainer::add_batch(self, entities);
+				}
+			}
+		);
+
 		let mut items = Vec::new();
 		items.push(structure.unwrap());
 		items.push(implementation.unwrap());
 		items.push(trait_impl.unwrap());
+		items.push(extend_impl.unwrap());
 
 		EntitiesGenerator(items)
 	}
@@ -116,6 +295,36 @@ impl EntitiesGenerator {
 		tokens
 	}
 
+	fn resource_decls(resources: &Resources) -> Tokens {
+		let mut tokens = vec!();
+
+		for (_, resource) in resources.iter() {
+			tokens.push_all(resource.field_decl.as_slice());
+		}
+
+		tokens
+	}
+
+	fn resource_inits(resources: &Resources) -> Tokens {
+		let mut tokens = vec!();
+
+		for (_, resource) in resources.iter() {
+			tokens.push_all(resource.field_init.as_slice());
+		}
+
+		tokens
+	}
+
+	fn capacities(components: &Components) -> Tokens {
+		let mut tokens = vec!();
+
+		for (_, component) in components.iter() {
+			tokens.push_all(component.collection_capacity.as_slice());
+		}
+
+		tokens
+	}
+
 	fn inserts(components: &Components) -> Tokens {
 		let mut tokens = Vec::new();
 
@@ -126,6 +335,26 @@ impl EntitiesGenerator {
 		tokens
 	}
 
+	fn addeds(components: &Components) -> Tokens {
+		let mut tokens = Vec::new();
+
+		for (_, component) in components.iter() {
+			tokens.push_all(component.added.as_slice());
+		}
+
+		tokens
+	}
+
+	fn methods(components: &Components) -> Tokens {
+		let mut tokens = Vec::new();
+
+		for (_, component) in components.iter() {
+			tokens.push_all(component.methods.as_slice());
+		}
+
+		tokens
+	}
+
 	fn removes(components: &Components) -> Tokens {
 		let mut removes = Vec::new();
 
@@ -136,6 +365,36 @@ impl EntitiesGenerator {
 		removes
 	}
 
+	fn reserves(components: &Components) -> Tokens {
+		let mut reserves = Vec::new();
+
+		for (_, component) in components.iter() {
+			reserves.push_all(component.reserve.as_slice());
+		}
+
+		reserves
+	}
+
+	fn shrinks(components: &Components) -> Tokens {
+		let mut shrinks = Vec::new();
+
+		for (_, component) in components.iter() {
+			shrinks.push_all(component.shrink.as_slice());
+		}
+
+		shrinks
+	}
+
+	fn clears(components: &Components) -> Tokens {
+		let mut clears = Vec::new();
+
+		for (_, component) in components.iter() {
+			clears.push_all(component.clear.as_slice());
+		}
+
+		clears
+	}
+
 	fn field_sets(components: &Components) -> Tokens {
 		let mut init = Vec::new();
 
@@ -145,6 +404,16 @@ impl EntitiesGenerator {
 
 		init
 	}
+
+	fn field_clones(components: &Components) -> Tokens {
+		let mut init = Vec::new();
+
+		for (_, component) in components.iter() {
+			init.push_all(component.field_clone.as_slice());
+		}
+
+		init
+	}
 }
 
 
@@ -218,36 +487,316 @@ impl EntityGenerator {
 }
 
 
+pub struct PrefabGenerator(pub Items);
+
+impl PrefabGenerator {
+	pub fn generate(context: &ExtCtxt, prefabs: &Prefabs) -> PrefabGenerator {
+		if prefabs.len() == 0 {
+			return PrefabGenerator(Vec::new());
+		}
+
+		let mut variants     = Vec::new();
+		let mut all          = Vec::new();
+		let mut names        = Vec::new();
+		let mut from_names   = Vec::new();
+		let mut entities     = Vec::new();
+		let mut constructors = Vec::new();
+		let mut spawners     = Vec::new();
+
+		for prefab in prefabs.iter() {
+			let name        = prefab.name;
+			let name_str    = name.as_str();
+			let constructor = prefab.constructor;
+			let spawner     = prefab.spawner;
+			let ref entity  = prefab.entity;
+
+			variants.push_all(
+				quote_tokens!(context,
+					$name,
+				)
+				.as_slice()
+			);
+			all.push_all(
+				quote_tokens!(context,
+					Prefab::$name,
+				)
+				.as_slice()
+			);
+			names.push_all(
+				quote_tokens!(context,
+					Prefab::$name => $name_str,
+				)
+				.as_slice()
+			);
+			from_names.push_all(
+				quote_tokens!(context,
+					$name_str => Some(Prefab::$name),
+				)
+				.as_slice()
+			);
+			entities.push_all(
+				quote_tokens!(context,
+					Prefab::$name => Entity::$constructor(),
+				)
+				.as_slice()
+			);
+			constructors.push_all(
+				quote_tokens!(context,
+					pub fn $constructor() -> Entity {
+						$entity
+					}
+				)
+				.as_slice()
+			);
+			spawners.push_all(
+				quote_tokens!(context,
+					fn $spawner(&mut self) -> _r::rustecs::EntityId {
+						self.spawn(Prefab::$name)
+					}
+				)
+				.as_slice()
+			);
+		}
+
+		let enumeration = quote_item!(context,
+			#[deriving(Clone, PartialEq, Eq, Show)]
+			pub enum Prefab {
+				$variants
+			}
+		);
+
+		let implementation = quote_item!(context,
+			impl Prefab {
+				pub fn all() -> Vec<Prefab> {
+					vec![$all]
+				}
+
+				pub fn name(&self) -> &'static str {
+					match *self {
+						$names
+					}
+				}
+
+				pub fn from_name(name: &str) -> Option<Prefab> {
+					match name {
+						$from_names
+						_ => None,
+					}
+				}
+
+				pub fn entity(&self) -> Entity {
+					match *self {
+						$entities
+					}
+				}
+			}
+		);
+
+		let constructors = quote_item!(context,
+			impl Entity {
+				$constructors
+			}
+		);
+
+		let spawn_trait = quote_item!(context,
+			pub trait Prefabs {
+				fn spawn(&mut self, prefab: Prefab) -> _r::rustecs::EntityId;
+
+				$spawners
+			}
+		);
+
+		let entities_impl = quote_item!(context,
+			impl Prefabs for Entities {
+				fn spawn(&mut self, prefab: Prefab) -> _r::rustecs::EntityId {
+					_r::rustecs::EntityContainer::add(self, prefab.entity())
+				}
+			}
+		);
+
+		let control_impl = quote_item!(context,
+			impl Prefabs for _r::rustecs::Control<Entity> {
+				fn spawn(&mut self, prefab: Prefab) -> _r::rustecs::EntityId {
+					self.add(prefab.entity())
+				}
+			}
+		);
+
+		PrefabGenerator(vec![
+			enumeration.unwrap(),
+			implementation.unwrap(),
+			constructors.unwrap(),
+			spawn_trait.unwrap(),
+			entities_impl.unwrap(),
+			control_impl.unwrap(),
+		])
+	}
+}
+
+
 pub struct EventGenerator(pub Items);
 
 impl EventGenerator {
 	pub fn generate(
-		context : &ExtCtxt,
-		events  : &Events,
-		deriving: &Tokens,
+		context       : &ExtCtxt,
+		events        : &Events,
+		cloneable     : bool,
+		deriving      : &Tokens,
+		event_deriving: &Tokens,
 	) -> EventGenerator {
-		let mut variants: Tokens = Vec::new();
+		let mut variants      : Tokens = Vec::new();
+		let mut owned_variants: Tokens = Vec::new();
+		let mut as_events     : Tokens = Vec::new();
+		let mut reborrows     : Tokens = Vec::new();
+		let mut to_owneds     : Tokens = Vec::new();
+		let mut kinds         : Tokens = Vec::new();
+		let mut to_kinds      : Tokens = Vec::new();
 		for event in events.iter() {
 			let name    = event.name;
 
+			kinds.push_all(
+				quote_tokens!(context,
+					$name,
+				)
+				.as_slice()
+			);
+			to_kinds.push_all(
+				quote_tokens!(context,
+					Event::$name(_) => EventKind::$name,
+				)
+				.as_slice()
+			);
+
 			variants.push_all(
 				quote_tokens!(context,
 					$name(&'r mut $name),
 				)
 				.as_slice()
 			);
+			owned_variants.push_all(
+				quote_tokens!(context,
+					$name($name),
+				)
+				.as_slice()
+			);
+			as_events.push_all(
+				quote_tokens!(context,
+					OwnedEvent::$name(ref mut event) => Event::$name(event),
+				)
+				.as_slice()
+			);
+			reborrows.push_all(
+				quote_tokens!(context,
+					Event::$name(ref mut event) => Event::$name(&mut **event),
+				)
+				.as_slice()
+			);
+			to_owneds.push_all(
+				quote_tokens!(context,
+					Event::$name(ref event) => OwnedEvent::$name((**event).clone()),
+				)
+				.as_slice()
+			);
 		}
 
 		let enumeration = quote_item!(context,
-			$deriving
+			$event_deriving
 			pub enum Event<'r> {
 				$variants
 			}
 		);
 
-		EventGenerator(vec![
+		let kind_enumeration = quote_item!(context,
+			#[deriving(Clone, PartialEq, Eq, Hash, Show)]
+			pub enum EventKind {
+				$kinds
+			}
+		);
+
+		let implementation = quote_item!(context,
+			impl<'r> Event<'r> {
+				pub fn kind(&self) -> EventKind {
+					match *self {
+						$to_kinds
+					}
+				}
+			}
+		);
+
+		let owned_enumeration = quote_item!(context,
+			$deriving
+			pub enum OwnedEvent {
+				$owned_variants
+			}
+		);
+
+		let owned_implementation = quote_item!(context,
+			impl OwnedEvent {
+				pub fn as_event(&mut self) -> Event {
+					match *self {
+						$as_events
+					}
+				}
+			}
+		);
+
+		// Lets Systems::trigger accept both borrowed and owned events.
+		let to_event_trait = quote_item!(context,
+			pub trait ToEvent {
+				fn to_event<'r>(&'r mut self) -> Event<'r>;
+			}
+		);
+		let to_event_impl = quote_item!(context,
+			impl<'a> ToEvent for Event<'a> {
+				fn to_event<'r>(&'r mut self) -> Event<'r> {
+					match *self {
+						$reborrows
+					}
+				}
+			}
+		);
+		let to_event_owned_impl = quote_item!(context,
+			impl ToEvent for OwnedEvent {
+				fn to_event<'r>(&'r mut self) -> Event<'r> {
+					self.as_event()
+				}
+			}
+		);
+
+		let mut items = vec![
 			enumeration.unwrap(),
-		])
+			kind_enumeration.unwrap(),
+			implementation.unwrap(),
+			owned_enumeration.unwrap(),
+			owned_implementation.unwrap(),
+			to_event_trait.unwrap(),
+			to_event_impl.unwrap(),
+			to_event_owned_impl.unwrap(),
+		];
+
+		// Turning a borrowed event into an owned one requires the event to
+		// implement Clone, which is only guaranteed if the world derives it.
+		if cloneable {
+			let to_owned = quote_item!(context,
+				impl<'r> Event<'r> {
+					pub fn to_owned(&self) -> OwnedEvent {
+						match *self {
+							$to_owneds
+						}
+					}
+				}
+			);
+
+			items.push(to_owned.unwrap());
+		}
+		for event in events.iter() {
+			match event.decl {
+				Some(ref decl) => items.push(decl.clone()),
+				None           => (),
+			}
+		}
+
+		EventGenerator(items)
 	}
 }
 
@@ -256,46 +805,527 @@ pub struct SystemsGenerator(pub Items);
 
 impl SystemsGenerator {
 	pub fn generate(
-		context : &ExtCtxt,
-		events  : &Events,
-		systems : Systems,
-		deriving: &Tokens,
+		context       : &ExtCtxt,
+		events        : &Events,
+		systems       : Systems,
+		schedules     : &Schedules,
+		fixed_timestep: &Option<FixedTimestep>,
+		error         : &Option<P<ast::Ty>>,
+		showable      : bool,
 	) -> SystemsGenerator {
+		let state_decls  = SystemsGenerator::state_decls(&systems);
+		let state_inits  = SystemsGenerator::state_inits(&systems);
 		let system_calls = SystemsGenerator::system_calls(
 			context,
 			events,
-			systems,
+			&systems,
+			error.is_some(),
+		);
+		let trigger_fns = SystemsGenerator::trigger_fns(events);
+		let run_fns     = SystemsGenerator::run_fns(schedules);
+
+		let fixed_timestep_fn = match *fixed_timestep {
+			Some(ref fixed_timestep) => fixed_timestep.run_fn.clone(),
+			None                     => Vec::new(),
+		};
+
+		let mut variants = Vec::new();
+		let mut indices  = Vec::new();
+		for (i, system) in systems.iter().enumerate() {
+			let variant = system.variant;
+
+			variants.push_all(
+				quote_tokens!(context,
+					$variant,
+				)
+				.as_slice()
+			);
+			indices.push_all(
+				quote_tokens!(context,
+					SystemName::$variant => $i,
+				)
+				.as_slice()
+			);
+		}
+		let num_systems = systems.len();
+
+		let system_name = quote_item!(context,
+			#[deriving(Clone, PartialEq, Eq, Hash, Show)]
+			pub enum SystemName {
+				$variants
+			}
+		);
+		let system_name_implementation = quote_item!(context,
+			impl SystemName {
+				fn index(&self) -> uint {
+					match *self {
+						$indices
+					}
+				}
+			}
+		);
+
+		// If the world declares an error type, triggering an event returns the
+		// errors of all systems that failed. Otherwise, nothing is returned.
+		let (
+			trigger_result,
+			error_decl,
+			error_init,
+			error_fns,
+			errors_decl,
+			skip_registered,
+			errors_result,
+			dispatch_error,
+			dispatch_trigger,
+			limit_exceeded,
+		) = match *error {
+			Some(ref error) => (
+				quote_tokens!(context,
+					Result<(), Vec<_r::rustecs::SystemError<$error>>>
+				),
+				quote_tokens!(context,
+					error_policy: _r::rustecs::ErrorPolicy,
+				),
+				quote_tokens!(context,
+					error_policy: _r::rustecs::ErrorPolicy::Abort,
+				),
+				quote_tokens!(context,
+					/// Decides whether the remaining systems for an event are
+					/// called, after one of them has failed. By default,
+					/// they're skipped.
+					pub fn set_error_policy(&mut self, error_policy: _r::rustecs::ErrorPolicy) {
+						self.error_policy = error_policy;
+					}
+
+					// Records the error, if the system failed. Returns whether
+					// the remaining systems should be skipped.
+					fn handle_result(
+						&self,
+						errors: &mut Vec<_r::rustecs::SystemError<$error>>,
+						system: &'static str,
+						event : &'static str,
+						result: Result<(), $error>,
+					) -> bool {
+						match result {
+							Ok(()) =>
+								false,
+							Err(error) => {
+								errors.push(_r::rustecs::SystemError {
+									system: system,
+									event : event,
+									error : error,
+								});
+
+								self.error_policy == _r::rustecs::ErrorPolicy::Abort
+							},
+						}
+					}
+				),
+				quote_tokens!(context,
+					let mut _errors = Vec::new();
+				),
+				quote_tokens!(context,
+					!_errors.is_empty()
+						&& self.error_policy == _r::rustecs::ErrorPolicy::Abort
+				),
+				quote_tokens!(context,
+					if _errors.is_empty() {
+						Ok(())
+					}
+					else {
+						Err(_errors)
+					}
+				),
+				quote_tokens!(context,
+					_r::rustecs::DispatchError<$error>
+				),
+				quote_tokens!(context,
+					match self.trigger(event, entities) {
+						Ok(()) =>
+							(),
+						Err(errors) =>
+							return Err(_r::rustecs::DispatchError::SystemsFailed(errors)),
+					}
+				),
+				quote_tokens!(context,
+					_r::rustecs::DispatchError::LimitExceeded
+				),
+			),
+
+			None => (
+				quote_tokens!(context, ()),
+				Vec::new(),
+				Vec::new(),
+				Vec::new(),
+				Vec::new(),
+				quote_tokens!(context, false),
+				quote_tokens!(context, ()),
+				quote_tokens!(context, _r::rustecs::DispatchLimitExceeded),
+				quote_tokens!(context,
+					self.trigger(event, entities);
+				),
+				Vec::new(),
+			),
+		};
+
+		let trigger_result = quote_item!(context,
+			pub type TriggerResult = $trigger_result;
 		);
 
+		let dynamic_system = quote_item!(context,
+			pub type DynamicSystem = Box<FnMut(&mut Event, &mut Entities) + 'static>;
+		);
+
+		// The profiler and the collection of timings are only available, if the
+		// profiling feature is enabled.
+		let (profiling_decl, profiling_init, profiling_fns) =
+			if cfg!(feature = "profiling") {
+				(
+					quote_tokens!(context,
+						profiler: _r::rustecs::ProfilerHook,
+						timings : Option<_r::rustecs::Timings>,
+					),
+					quote_tokens!(context,
+						profiler: _r::rustecs::ProfilerHook::new(),
+						timings : None,
+					),
+					quote_tokens!(context,
+						/// The profiler is notified whenever a system is started and
+						/// whenever it has finished.
+						pub fn set_profiler(&mut self, profiler: Box<_r::rustecs::Profiler + 'static>) {
+							self.profiler.set(profiler);
+						}
+
+						pub fn remove_profiler(&mut self) -> Option<Box<_r::rustecs::Profiler + 'static>> {
+							self.profiler.take()
+						}
+
+						/// Enables or disables the built-in collection of the
+						/// durations of each system's calls. Disabling it discards the
+						/// collected timings.
+						pub fn set_collect_timings(&mut self, collect_timings: bool) {
+							self.timings = if collect_timings {
+								Some(_r::rustecs::Timings::new())
+							}
+							else {
+								None
+							};
+						}
+
+						pub fn timings(&mut self) -> Option<&mut _r::rustecs::Timings> {
+							self.timings.as_mut()
+						}
+
+						fn start_profiling(&mut self, system: &str, event: &str, time_ns: u64) {
+							match self.timings {
+								Some(ref mut timings) =>
+									_r::rustecs::Profiler::start(timings, system, event, time_ns),
+								None =>
+									(),
+							}
+							self.profiler.start(system, event, time_ns);
+						}
+
+						fn end_profiling(&mut self, system: &str, event: &str, time_ns: u64) {
+							self.profiler.end(system, event, time_ns);
+							match self.timings {
+								Some(ref mut timings) =>
+									_r::rustecs::Profiler::end(timings, system, event, time_ns),
+								None =>
+									(),
+							}
+						}
+					),
+				)
+			}
+			else {
+				(Vec::new(), Vec::new(), Vec::new())
+			};
+
+		// Non-conflicting systems run on the threads of a pool, which is only
+		// available, if the parallel feature is enabled.
+		let (pool_decl, pool_init, pool_fns) = if cfg!(feature = "parallel") {
+			(
+				quote_tokens!(context,
+					pool: _r::rustecs::ThreadPool,
+				),
+				quote_tokens!(context,
+					pool: _r::rustecs::ThreadPool::new(::std::os::num_cpus()),
+				),
+				quote_tokens!(context,
+					/// Sets the number of threads that non-conflicting systems
+					/// run on. By default, there's one per CPU. Panics, if
+					/// `threads` is 0.
+					pub fn set_threads(&mut self, threads: uint) {
+						self.pool = _r::rustecs::ThreadPool::new(threads);
+					}
+				),
+			)
+		}
+		else {
+			(Vec::new(), Vec::new(), Vec::new())
+		};
+
 		let structure = quote_item!(context,
-			$deriving
-			pub struct Systems;
+			pub struct Systems {
+				control       : _r::rustecs::Control<Entity>,
+				apply_control : _r::rustecs::ApplyControl,
+				events        : _r::rustecs::EventQueue<OwnedEvent>,
+				dispatch_limit: uint,
+				registry      : _r::rustecs::Registry<EventKind, DynamicSystem>,
+				enabled       : Vec<bool>,
+				$profiling_decl
+				$pool_decl
+				$error_decl
+
+				$state_decls
+			}
 		);
 
 		let implementation = quote_item!(context,
 			impl Systems {
 				pub fn new() -> Systems {
-					Systems
+					Systems {
+						control       : _r::rustecs::Control::new(),
+						apply_control : _r::rustecs::ApplyControl::AfterTrigger,
+						events        : _r::rustecs::EventQueue::new(),
+						dispatch_limit: _r::rustecs::DEFAULT_DISPATCH_LIMIT,
+						registry      : _r::rustecs::Registry::new(),
+						enabled       : Vec::from_elem($num_systems, true),
+						$profiling_init
+						$pool_init
+						$error_init
+
+						$state_inits
+					}
 				}
 
-				pub fn trigger(&self, event: Event, _entities: &mut Entities) {
-					match event {
+				/// The control that is passed to systems. Use it instead of
+				/// creating your own, as the ids of entities added through
+				/// different controls might collide.
+				pub fn control(&mut self) -> &mut _r::rustecs::Control<Entity> {
+					&mut self.control
+				}
+
+				pub fn set_apply_control(&mut self, apply_control: _r::rustecs::ApplyControl) {
+					self.apply_control = apply_control;
+				}
+
+				pub fn apply_control(&mut self, entities: &mut Entities) {
+					self.control.apply(entities);
+				}
+
+				/// The queue of events that are triggered by `dispatch_all`.
+				/// Systems can push events into it by listing `events` with
+				/// their components.
+				pub fn events(&mut self) -> &mut _r::rustecs::EventQueue<OwnedEvent> {
+					&mut self.events
+				}
+
+				pub fn set_dispatch_limit(&mut self, dispatch_limit: uint) {
+					self.dispatch_limit = dispatch_limit;
+				}
+
+				/// Disabled systems aren't called, until they're enabled
+				/// again. All systems start out enabled.
+				pub fn set_enabled(&mut self, system: SystemName, enabled: bool) {
+					self.enabled[system.index()] = enabled;
+				}
+
+				pub fn is_enabled(&self, system: SystemName) -> bool {
+					self.enabled[system.index()]
+				}
+
+				$profiling_fns
+
+				$pool_fns
+
+				$error_fns
+
+				/// Registers a system that is called whenever an event of the
+				/// given kind is triggered, after the declared systems. The
+				/// returned handle can be used to unregister it again.
+				pub fn register(&mut self, kind: EventKind, system: DynamicSystem)
+					-> _r::rustecs::SystemHandle
+				{
+					self.registry.register(kind, system)
+				}
+
+				/// Returns whether a system with the given handle was
+				/// registered.
+				pub fn unregister(&mut self, handle: _r::rustecs::SystemHandle) -> bool {
+					self.registry.unregister(handle)
+				}
+
+				/// Triggers queued events, until the queue is empty. This
+				/// includes events that are queued by systems in the process.
+				/// Returns the number of dispatched events.
+				pub fn dispatch_all(&mut self, entities: &mut Entities)
+					-> Result<uint, $dispatch_error>
+				{
+					let mut dispatched = 0;
+
+					loop {
+						if self.events.is_empty() {
+							return Ok(dispatched);
+						}
+						if dispatched == self.dispatch_limit {
+							return Err($limit_exceeded(_r::rustecs::DispatchLimitExceeded {
+								dispatched: dispatched,
+								remaining : self.events.len(),
+							}));
+						}
+
+						let event = self.events.pop().unwrap();
+						$dispatch_trigger
+
+						dispatched += 1;
+					}
+				}
+
+				pub fn trigger<E: ToEvent>(&mut self, event: E, entities: &mut Entities)
+					-> TriggerResult
+				{
+					self.trigger_targets(event, None, entities)
+				}
+
+				/// Triggers the event for the given entities only. Systems that
+				/// are declared with `for_components` are called for each of
+				/// the targets that has all of the components, once per target
+				/// and in the order of the targets' ids. All other systems are
+				/// called as usual.
+				pub fn trigger_for<E: ToEvent>(
+					&mut self,
+					event   : E,
+					targets : &[_r::rustecs::EntityId],
+					entities: &mut Entities,
+				) -> TriggerResult {
+					let mut targets = targets.to_vec();
+					targets.sort();
+					targets.dedup();
+
+					self.trigger_targets(event, Some(targets.as_slice()), entities)
+				}
+
+				fn trigger_targets<E: ToEvent>(
+					&mut self,
+					mut event: E,
+					_targets : Option<&[_r::rustecs::EntityId]>,
+					_entities: &mut Entities,
+				) -> TriggerResult {
+					$errors_decl
+
+					match event.to_event() {
 						$system_calls
 					}
+
+					if !($skip_registered) {
+						let mut event = event.to_event();
+						let kind      = event.kind();
+						for &mut (_, ref registered_kind, ref mut system) in self.registry.iter_mut() {
+							if *registered_kind == kind {
+								(*system)(&mut event, _entities);
+							}
+						}
+					}
+
+					if self.apply_control == _r::rustecs::ApplyControl::AfterTrigger {
+						self.control.apply(_entities);
+					}
+
+					$errors_result
 				}
+
+				$trigger_fns
+
+				$run_fns
+
+				$fixed_timestep_fn
 			}
 		);
 
-		SystemsGenerator(vec![
+		let mut items = vec![
+			trigger_result.unwrap(),
+			system_name.unwrap(),
+			system_name_implementation.unwrap(),
+			dynamic_system.unwrap(),
 			structure.unwrap(),
 			implementation.unwrap(),
-		])
+		];
+
+		// Systems holds registered closures and the profiler, so the world's
+		// traits can't be derived for it. Show is implemented by hand for the
+		// parts that can be shown.
+		if showable {
+			let show = quote_item!(context,
+				impl ::std::fmt::Show for Systems {
+					fn fmt(&self, formatter: &mut ::std::fmt::Formatter)
+						-> ::std::fmt::Result
+					{
+						write!(
+							formatter,
+							"Systems {{ control: {}, apply_control: {}, events: {}, dispatch_limit: {}, enabled: {} }}",
+							self.control,
+							self.apply_control,
+							self.events,
+							self.dispatch_limit,
+							self.enabled
+						)
+					}
+				}
+			);
+
+			items.push(show.unwrap());
+		}
+
+		SystemsGenerator(items)
+	}
+
+	fn state_decls(systems: &Systems) -> Tokens {
+		let mut tokens = Vec::new();
+
+		for system in systems.iter() {
+			tokens.push_all(system.state_decl.as_slice());
+		}
+
+		tokens
+	}
+
+	fn state_inits(systems: &Systems) -> Tokens {
+		let mut tokens = Vec::new();
+
+		for system in systems.iter() {
+			tokens.push_all(system.state_init.as_slice());
+		}
+
+		tokens
+	}
+
+	fn trigger_fns(events: &Events) -> Tokens {
+		let mut tokens = Vec::new();
+
+		for event in events.iter() {
+			tokens.push_all(event.trigger_fn.as_slice());
+		}
+
+		tokens
+	}
+
+	fn run_fns(schedules: &Schedules) -> Tokens {
+		let mut tokens = Vec::new();
+
+		for schedule in schedules.iter() {
+			tokens.push_all(schedule.run_fn.as_slice());
+		}
+
+		tokens
 	}
 
 	fn system_calls(
-		context: &ExtCtxt,
-		events : &Events,
-		systems: Systems
+		context : &ExtCtxt,
+		events  : &Events,
+		systems : &Systems,
+		fallible: bool,
 	) -> Tokens {
 		let mut tokens = Vec::new();
 
@@ -304,16 +1334,49 @@ impl SystemsGenerator {
 
 			let name = event.name;
 
-			let mut iter = systems
+			let systems_for_event: Vec<&System> = systems
 				.iter()
 				.filter(
 					|system|
-						system.event == name
+						system.call_for(name).is_some()
+				)
+				.collect();
+			for system in systems_for_event.iter() {
+				calls_for_event.push_all(
+					system.call_for(name).unwrap().call.as_slice()
 				);
-			for system in iter {
-				calls_for_event.push_all(system.call.as_slice());
 			}
 
+			if cfg!(feature = "parallel") {
+				let mut batch_calls = Vec::new();
+				for batch in parallel::batches(systems_for_event.as_slice()).iter() {
+					batch_calls.push_all(
+						parallel::batch_call(context, name, batch.as_slice()).as_slice()
+					);
+				}
+
+				// Applying the control after every system only makes sense, if
+				// the systems run one after the other.
+				calls_for_event = quote_tokens!(context,
+					if self.apply_control == _r::rustecs::ApplyControl::AfterSystem {
+						$calls_for_event
+					}
+					else {
+						$batch_calls
+					}
+				);
+			}
+
+			// Failing systems can skip the remaining ones by breaking out of
+			// this loop.
+			if fallible {
+				calls_for_event = quote_tokens!(context,
+					'systems: loop {
+						$calls_for_event
+						break;
+					}
+				);
+			}
 
 			tokens.push_all(
 				quote_tokens!(context,
@@ -0,0 +1,219 @@
+use syntax::ast;
+use syntax::ext::base::ExtCtxt;
+use syntax::parse::token;
+
+use super::Tokens;
+use super::intermediate::System;
+
+
+/// Groups consecutive systems that don't conflict with each other into
+/// batches. The systems of a batch can run at the same time, while the batches
+/// themselves run one after the other.
+pub fn batches<'a>(systems: &[&'a System]) -> Vec<Vec<&'a System>> {
+	let mut batches: Vec<Vec<&System>> = Vec::new();
+
+	for &system in systems.iter() {
+		let fits = match batches.last() {
+			Some(batch) =>
+				batch.iter().all(|other| !system.conflicts_with(*other)),
+			None =>
+				false,
+		};
+
+		if fits {
+			batches.last_mut().unwrap().push(system);
+		}
+		else {
+			batches.push(vec![system]);
+		}
+	}
+
+	batches
+}
+
+/// Calls the systems of the batch on the threads of the pool and waits for all
+/// of them to finish. Conditions are checked before any of the systems is
+/// started. Each job records when its system started and finished, and the
+/// profiler is notified with those times, after all of them have finished.
+pub fn batch_call(context: &ExtCtxt, event: ast::Ident, batch: &[&System])
+	-> Tokens
+{
+	if batch.len() == 1 {
+		return batch[0].call_for(event).unwrap().call.clone();
+	}
+
+	let profiling = cfg!(feature = "profiling");
+
+	let mut outer = Vec::new();
+	let mut inner = Vec::new();
+	let mut jobs  = Vec::new();
+	let mut ends  = Vec::new();
+
+	for (i, system) in batch.iter().enumerate() {
+		let name = system.name;
+		let call = system.call_for(event).unwrap();
+
+		let run      = ident(format!("_run_{}", i));
+		let result   = ident(format!("_result_{}", i));
+		let slot     = ident(format!("_result_slot_{}", i));
+		let started  = ident(format!("_started_{}", i));
+		let finished = ident(format!("_finished_{}", i));
+		let times    = ident(format!("_times_slot_{}", i));
+
+		let system_name = name.as_str();
+		let event_name  = event.as_str();
+
+		let ref condition = call.condition;
+		outer.push_all(
+			quote_tokens!(context,
+				let $run = $condition;
+			)
+			.as_slice()
+		);
+
+		// The arguments are borrowed before the jobs are passed to the pool,
+		// so every job only captures the data its system needs.
+		let mut args = Vec::new();
+		for (j, arg) in call.args.iter().enumerate() {
+			let local = ident(format!("_arg_{}_{}", i, j));
+
+			inner.push_all(
+				quote_tokens!(context,
+					let $local = $arg;
+				)
+				.as_slice()
+			);
+			args.push_all(
+				quote_tokens!(context,
+					$local,
+				)
+				.as_slice()
+			);
+		}
+
+		let invocation = if system.fallible {
+			outer.push_all(
+				quote_tokens!(context,
+					let mut $result = None;
+				)
+				.as_slice()
+			);
+			inner.push_all(
+				quote_tokens!(context,
+					let $slot = &mut $result;
+				)
+				.as_slice()
+			);
+			ends.push_all(
+				quote_tokens!(context,
+					match $result {
+						Some(result) =>
+							if self.handle_result(&mut _errors, $system_name, $event_name, result) {
+								_abort = true;
+							},
+						None =>
+							(),
+					}
+				)
+				.as_slice()
+			);
+
+			quote_tokens!(context,
+				*$slot = Some(
+					_r::rustecs::IntoSystemResult::into_system_result($name($args))
+				);
+			)
+		}
+		else {
+			quote_tokens!(context,
+				$name($args);
+			)
+		};
+
+		let job = if profiling {
+			outer.push_all(
+				quote_tokens!(context,
+					let mut $started  = 0u64;
+					let mut $finished = 0u64;
+				)
+				.as_slice()
+			);
+			inner.push_all(
+				quote_tokens!(context,
+					let $times = (&mut $started, &mut $finished);
+				)
+				.as_slice()
+			);
+			ends.push_all(
+				quote_tokens!(context,
+					if $run {
+						self.start_profiling($system_name, $event_name, $started);
+						self.end_profiling($system_name, $event_name, $finished);
+					}
+				)
+				.as_slice()
+			);
+
+			quote_tokens!(context,
+				let (started, finished) = $times;
+				*started = _r::rustecs::precise_time_ns();
+				$invocation
+				*finished = _r::rustecs::precise_time_ns();
+			)
+		}
+		else {
+			invocation
+		};
+
+		jobs.push_all(
+			quote_tokens!(context,
+				if $run {
+					_scope.execute(move || {
+						$job
+					});
+				}
+			)
+			.as_slice()
+		);
+	}
+
+	// All systems of the batch have finished, before the remaining systems are
+	// skipped because of an error.
+	let (abort_decl, abort) = if batch.iter().any(|system| system.fallible) {
+		(
+			quote_tokens!(context,
+				let mut _abort = false;
+			),
+			quote_tokens!(context,
+				if _abort {
+					break 'systems;
+				}
+			),
+		)
+	}
+	else {
+		(Vec::new(), Vec::new())
+	};
+
+	// The jobs borrow parts of `self` until the end of their block, so the
+	// results can only be handled and the profiler can only be notified after
+	// that.
+	quote_tokens!(context,
+		{
+			$abort_decl
+			$outer
+			{
+				$inner
+				self.pool.scoped(move |_scope| {
+					$jobs
+				});
+			}
+			$ends
+			$abort
+		}
+	)
+}
+
+fn ident(name: String) -> ast::Ident {
+	ast::Ident::new(token::intern(name.as_slice()))
+}
@@ -17,6 +17,30 @@ pub fn camel_to_snake_case(ident: ast::Ident) -> String {
 	snake_case
 }
 
+pub fn snake_to_camel_case(ident: ast::Ident) -> String {
+	let snake_case = token::get_ident(ident).to_string().into_ascii();
+
+	let mut camel_case = String::new();
+	let mut uppercase  = true;
+	for c in snake_case.iter() {
+		if c.as_char() == '_' {
+			uppercase = true;
+			continue;
+		}
+
+		if uppercase {
+			camel_case.push(c.to_uppercase().as_char());
+		}
+		else {
+			camel_case.push(c.as_char());
+		}
+
+		uppercase = false;
+	}
+
+	camel_case
+}
+
 pub fn type_to_collection_name(ident: ast::Ident) -> String {
 	pluralize(camel_to_snake_case(ident))
 }
@@ -1,8 +1,16 @@
 use syntax::ast;
+use syntax::codemap;
 use syntax::ext::base::ExtCtxt;
 use syntax::parse;
 use syntax::parse::parser::{ Parser, PathParsingMode };
 use syntax::parse::token;
+use syntax::parse::token::keywords;
+use syntax::ptr::P;
+
+use names::{
+	camel_to_snake_case,
+	type_to_collection_name,
+};
 
 
 pub fn parse(context: &ExtCtxt, token_tree: &[ast::TokenTree]) -> World {
@@ -19,17 +27,31 @@ pub fn parse(context: &ExtCtxt, token_tree: &[ast::TokenTree]) -> World {
 #[deriving(Show)]
 pub struct World {
 	pub components    : Vec<ast::Path>,
-	pub events        : Vec<ast::Ident>,
+	pub resources     : Vec<ast::Path>,
+	pub events        : Vec<Event>,
 	pub systems       : Vec<System>,
+	pub schedules     : Vec<Schedule>,
+	pub prefabs       : Vec<Prefab>,
 	pub derived_traits: Vec<ast::Ident>,
+	pub import_policy : Option<ast::Ident>,
+	pub error         : Option<P<ast::Ty>>,
+	pub fixed_timestep: Option<FixedTimestep>,
+	pub hooks         : Vec<Hook>,
 }
 
 impl World {
 	fn parse(parser: &mut Parser) -> World {
 		let mut components     = Vec::new();
+		let mut resources      = Vec::new();
 		let mut events         = Vec::new();
 		let mut systems        = Vec::new();
+		let mut schedules      = Vec::new();
+		let mut prefabs        = Vec::new();
 		let mut derived_traits = Vec::new();
+		let mut import_policy  = None;
+		let mut error          = None;
+		let mut fixed_timestep = None;
+		let mut hooks          = Vec::new();
 
 		loop {
 			let declaration = parser.parse_ident();
@@ -45,9 +67,20 @@ impl World {
 					}
 				},
 
+				"resources" => {
+					loop {
+						resources.push(parser.parse_path(PathParsingMode::LifetimeAndTypesWithoutColons));
+
+						parser.eat(&token::Comma);
+						if parser.eat(&token::Semi) {
+							break;
+						}
+					}
+				},
+
 				"events" => {
 					loop {
-						events.push(parser.parse_ident());
+						events.push(Event::parse(parser));
 
 						parser.eat(&token::Comma);
 						if parser.eat(&token::Semi) {
@@ -60,6 +93,14 @@ impl World {
 					systems.push(System::parse(parser));
 				},
 
+				"schedule" => {
+					schedules.push(Schedule::parse(parser));
+				},
+
+				"prefab" => {
+					prefabs.push(Prefab::parse(parser));
+				},
+
 				"derived_traits" => {
 					loop {
 						derived_traits.push(parser.parse_ident());
@@ -71,6 +112,47 @@ impl World {
 					}
 				},
 
+				"import_policy" => {
+					let policy = parser.parse_ident();
+					match policy.as_str() {
+						"Replace" | "Merge" | "Reject" =>
+							(),
+
+						_ =>
+							parser.fatal(
+								format!(
+									"Expected Replace, Merge or Reject, found {}",
+									policy.as_str(),
+								)
+								.as_slice()
+							)
+					}
+
+					import_policy = Some(policy);
+					parser.expect(&token::Semi);
+				},
+
+				"error" => {
+					error = Some(parser.parse_ty());
+					parser.expect(&token::Semi);
+				},
+
+				"fixed_timestep" => {
+					if fixed_timestep.is_some() {
+						parser.fatal("Only one fixed timestep can be declared");
+					}
+
+					fixed_timestep = Some(FixedTimestep::parse(parser));
+				},
+
+				"on_add" => {
+					hooks.push(Hook::parse(parser, HookKind::Add));
+				},
+
+				"on_remove" => {
+					hooks.push(Hook::parse(parser, HookKind::Remove));
+				},
+
 				_ =>
 					parser.fatal(
 						format!(
@@ -86,11 +168,245 @@ impl World {
 			}
 		}
 
+		World::check_components(parser, components.as_slice());
+		World::check_events(parser, events.as_slice());
+		World::check_schedules(parser, schedules.as_slice());
+		World::check_prefabs(parser, components.as_slice(), prefabs.as_slice());
+		World::check_resources(parser, components.as_slice(), resources.as_slice());
+
 		World {
 			components    : components,
+			resources     : resources,
 			events        : events,
 			systems       : systems,
+			schedules     : schedules,
+			prefabs       : prefabs,
 			derived_traits: derived_traits,
+			import_policy : import_policy,
+			error         : error,
+			fixed_timestep: fixed_timestep,
+			hooks         : hooks,
+		}
+	}
+
+	// Every component gets an `insert_` and a `remove_` method on the generated
+	// `Entities`, named after the component. Those must neither clash with each
+	// other nor with the methods `Entities` already has, including the ones
+	// from `EntityContainer`.
+	fn check_components(parser: &mut Parser, components: &[ast::Path]) {
+		let mut methods: Vec<String> = [
+			"new", "with_capacity", "reserve", "shrink_to_fit", "clear",
+			"reset", "len", "is_empty", "contains", "ids", "duplicate", "add",
+			"import", "import_with", "remove", "duplicate_as", "add_batch",
+			"remove_batch", "export",
+		]
+		.iter()
+		.map(|method| method.to_string())
+		.collect();
+
+		for component in components.iter() {
+			let ident = component.segments.last().unwrap().identifier;
+			let name  = camel_to_snake_case(ident);
+
+			let generated = vec![
+				format!("insert_{}", name),
+				format!("remove_{}", name),
+			];
+
+			for method in generated.into_iter() {
+				if methods.contains(&method) {
+					parser.span_fatal(
+						component.span,
+						format!(
+							"Component {} would get the method {} on Entities, which already exists",
+							ident.as_str(),
+							method,
+						)
+						.as_slice()
+					);
+				}
+
+				methods.push(method);
+			}
+		}
+	}
+
+	// Every event gets a trigger method on the generated `Systems`, named after
+	// the event. Those must neither clash with each other nor with the trigger
+	// methods `Systems` already has.
+	fn check_events(parser: &mut Parser, events: &[Event]) {
+		let mut methods: Vec<String> = ["trigger", "trigger_for", "trigger_targets"]
+			.iter()
+			.map(|method| method.to_string())
+			.collect();
+
+		for event in events.iter() {
+			let method = format!("trigger_{}", camel_to_snake_case(event.name));
+
+			if methods.contains(&method) {
+				parser.span_fatal(
+					event.span,
+					format!(
+						"Event {} would get the method {} on Systems, which already exists",
+						event.name.as_str(),
+						method,
+					)
+					.as_slice()
+				);
+			}
+
+			methods.push(method);
+		}
+	}
+
+	// Every schedule gets a run method on the generated `Systems`, named after
+	// the schedule. Those must neither clash with each other nor with the
+	// method that runs the fixed timestep.
+	fn check_schedules(parser: &mut Parser, schedules: &[Schedule]) {
+		let mut methods = vec!["run_fixed_timestep".to_string()];
+
+		for schedule in schedules.iter() {
+			let method = format!("run_{}", camel_to_snake_case(schedule.name));
+
+			if methods.contains(&method) {
+				parser.span_fatal(
+					schedule.span,
+					format!(
+						"Schedule {} would get the method {} on Systems, which already exists",
+						schedule.name.as_str(),
+						method,
+					)
+					.as_slice()
+				);
+			}
+
+			methods.push(method);
+		}
+	}
+
+	// Every prefab gets a constructor on the generated `Entity`, named after the
+	// prefab. It must neither clash with `Entity::new`, nor with the builder
+	// methods of the components, nor with the constructors of other prefabs.
+	fn check_prefabs(
+		parser    : &mut Parser,
+		components: &[ast::Path],
+		prefabs   : &[Prefab],
+	) {
+		let mut methods = vec!["new".to_string()];
+		for component in components.iter() {
+			methods.push(format!(
+				"with_{}",
+				camel_to_snake_case(component.segments.last().unwrap().identifier),
+			));
+		}
+
+		for prefab in prefabs.iter() {
+			let constructor = camel_to_snake_case(prefab.name);
+
+			if methods.contains(&constructor) {
+				parser.span_fatal(
+					prefab.span,
+					format!(
+						"Prefab {} would get the constructor {} on Entity, which already exists",
+						prefab.name.as_str(),
+						constructor,
+					)
+					.as_slice()
+				);
+			}
+
+			methods.push(constructor);
+		}
+	}
+
+	// Resources are stored in fields of the generated `Entities`, next to the
+	// component collections. Systems look up the names they ask for among the
+	// components first, so a resource with a component's name would never be
+	// passed to them.
+	fn check_resources(
+		parser    : &mut Parser,
+		components: &[ast::Path],
+		resources : &[ast::Path],
+	) {
+		let mut fields = vec!["entities".to_string(), "next_id".to_string()];
+		for component in components.iter() {
+			fields.push(type_to_collection_name(
+				component.segments.last().unwrap().identifier
+			));
+		}
+
+		for resource in resources.iter() {
+			let ident = resource.segments.last().unwrap().identifier;
+			let field = camel_to_snake_case(ident);
+
+			let is_component = components.iter().any(|component|
+				component.segments.last().unwrap().identifier == ident
+			);
+			if is_component {
+				parser.span_fatal(
+					resource.span,
+					format!(
+						"{} is declared as both a component and a resource",
+						ident.as_str(),
+					)
+					.as_slice()
+				);
+			}
+
+			if fields.contains(&field) {
+				parser.span_fatal(
+					resource.span,
+					format!(
+						"Resource {} would be stored in the field {} of Entities, which is already in use",
+						ident.as_str(),
+						field,
+					)
+					.as_slice()
+				);
+			}
+		}
+	}
+}
+
+
+#[deriving(Show)]
+pub struct Event {
+	pub name  : ast::Ident,
+	pub params: Option<Vec<(ast::Ident, P<ast::Ty>)>>,
+	pub span  : codemap::Span,
+}
+
+impl Event {
+	fn parse(parser: &mut Parser) -> Event {
+		let span = parser.span;
+		let name = parser.parse_ident();
+
+		let params = if parser.eat(&token::OpenDelim(token::Paren)) {
+			let mut params = Vec::new();
+			loop {
+				if parser.eat(&token::CloseDelim(token::Paren)) {
+					break;
+				}
+
+				let param = parser.parse_ident();
+				parser.expect(&token::Colon);
+				let ty = parser.parse_ty();
+
+				params.push((param, ty));
+
+				parser.eat(&token::Comma);
+			}
+
+			Some(params)
+		}
+		else {
+			None
+		};
+
+		Event {
+			name  : name,
+			params: params,
+			span  : span,
 		}
 	}
 }
@@ -99,41 +415,84 @@ impl World {
 #[deriving(Show)]
 pub struct System {
 	pub name      : ast::Ident,
-	pub event     : ast::Ident,
-	pub components: Vec<ast::Ident>,
+	pub events    : Vec<ast::Ident>,
+	pub args      : Vec<Arg>,
+	pub components: Vec<Arg>,
+	pub before    : Vec<ast::Ident>,
+	pub after     : Vec<ast::Ident>,
+	pub state     : Option<P<ast::Ty>>,
+	pub conditions: Vec<ast::Ident>,
+	pub span      : codemap::Span,
 }
 
 impl System {
 	fn parse(parser: &mut Parser) -> System {
+		let span = parser.span;
 		let name = parser.parse_ident();
 
-		let mut event     : Option<ast::Ident> = None;
-		let mut components: Vec<ast::Ident>    = Vec::new();
+		let mut events    : Vec<ast::Ident>    = Vec::new();
+		let mut args      : Vec<Arg>           = Vec::new();
+		let mut components: Vec<Arg>           = Vec::new();
+		let mut before    : Vec<ast::Ident>    = Vec::new();
+		let mut after     : Vec<ast::Ident>    = Vec::new();
+		let mut state     : Option<P<ast::Ty>> = None;
+		let mut conditions: Vec<ast::Ident>    = Vec::new();
 
 		loop {
 			let system_declaration = parser.parse_ident();
 			match system_declaration.as_str() {
 				"on" => {
-					parser.expect(&token::OpenDelim(token::Paren));
-					event = Some(parser.parse_ident());
-					parser.expect(&token::CloseDelim(token::Paren));
+					let on_span = parser.span;
+
+					// A system that listens to an event twice would be called
+					// with the generic event, but only once.
+					for event in parse_idents(parser).into_iter() {
+						if events.contains(&event) {
+							parser.span_fatal(
+								on_span,
+								format!(
+									"System {} listens to event {} more than once",
+									name.as_str(),
+									event.as_str(),
+								)
+								.as_slice()
+							);
+						}
+
+						events.push(event);
+					}
 				},
 
 				"with" => {
+					args.push_all(parse_args(parser).as_slice());
+				},
+
+				"for_components" => {
+					components.push_all(parse_args(parser).as_slice());
+				},
+
+				"before" => {
+					before.push_all(parse_idents(parser).as_slice());
+				},
+
+				"after" => {
+					after.push_all(parse_idents(parser).as_slice());
+				},
+
+				"when" => {
+					conditions.push_all(parse_idents(parser).as_slice());
+				},
+
+				"state" => {
 					parser.expect(&token::OpenDelim(token::Paren));
-					loop {
-						components.push(parser.parse_ident());
-						parser.eat(&token::Comma);
-						if parser.eat(&token::CloseDelim(token::Paren)) {
-							break;
-						}
-					}
+					state = Some(parser.parse_ty());
+					parser.expect(&token::CloseDelim(token::Paren));
 				},
 
 				_ =>
 					parser.fatal(
 						format!(
-							"Expected 'on' or 'with', found{}",
+							"Expected 'on', 'with', 'for_components', 'before', 'after', 'when' or 'state', found {}",
 							system_declaration.as_str(),
 						)
 						.as_slice()
@@ -145,14 +504,237 @@ impl System {
 			}
 		}
 
-		let event = event.unwrap_or_else(|| {
+		if events.is_empty() {
 			parser.fatal("You need to specify an event");
-		});
+		}
 
 		System {
 			name      : name,
-			event     : event,
+			events    : events,
+			args      : args,
+			components: components,
+			before    : before,
+			after     : after,
+			state     : state,
+			conditions: conditions,
+			span      : span,
+		}
+	}
+}
+
+
+#[deriving(Clone, PartialEq, Show)]
+pub enum Access {
+	Read,
+	Write,
+}
+
+/// Something a system asks for, like a component or a resource.
+#[deriving(Clone, Show)]
+pub struct Arg {
+	pub name  : ast::Ident,
+	pub access: Access,
+}
+
+impl Arg {
+	fn parse(parser: &mut Parser) -> Arg {
+		// Without explicit access, systems get mutable access to everything.
+		let access = if parser.eat(&token::BinOp(token::And)) {
+			if parser.eat_keyword(keywords::Mut) {
+				Access::Write
+			}
+			else {
+				Access::Read
+			}
+		}
+		else {
+			Access::Write
+		};
+
+		Arg {
+			name  : parser.parse_ident(),
+			access: access,
+		}
+	}
+}
+
+
+// Parses a parenthesized, comma-separated list of system arguments.
+fn parse_args(parser: &mut Parser) -> Vec<Arg> {
+	let mut args = Vec::new();
+
+	parser.expect(&token::OpenDelim(token::Paren));
+	loop {
+		args.push(Arg::parse(parser));
+		parser.eat(&token::Comma);
+		if parser.eat(&token::CloseDelim(token::Paren)) {
+			break;
+		}
+	}
+
+	args
+}
+
+// Parses a parenthesized, comma-separated list of identifiers.
+fn parse_idents(parser: &mut Parser) -> Vec<ast::Ident> {
+	let mut idents = Vec::new();
+
+	parser.expect(&token::OpenDelim(token::Paren));
+	loop {
+		idents.push(parser.parse_ident());
+		parser.eat(&token::Comma);
+		if parser.eat(&token::CloseDelim(token::Paren)) {
+			break;
+		}
+	}
+
+	idents
+}
+
+
+#[deriving(Show)]
+pub struct Schedule {
+	pub name  : ast::Ident,
+	pub stages: Vec<ast::Ident>,
+	pub span  : codemap::Span,
+}
+
+impl Schedule {
+	fn parse(parser: &mut Parser) -> Schedule {
+		let span = parser.span;
+		let name = parser.parse_ident();
+		parser.expect(&token::Eq);
+
+		let mut stages: Vec<ast::Ident> = Vec::new();
+		loop {
+			let stage_span = parser.span;
+			let stage      = parser.parse_ident();
+
+			// Each stage that has parameters becomes a parameter of the
+			// schedule's run method, which would then be declared twice.
+			if stages.contains(&stage) {
+				parser.span_fatal(
+					stage_span,
+					format!(
+						"Schedule {} contains event {} more than once",
+						name.as_str(),
+						stage.as_str(),
+					)
+					.as_slice()
+				);
+			}
+
+			stages.push(stage);
+
+			parser.eat(&token::Comma);
+			if parser.eat(&token::Semi) {
+				break;
+			}
+		}
+
+		Schedule {
+			name  : name,
+			stages: stages,
+			span  : span,
+		}
+	}
+}
+
+
+#[deriving(Show)]
+pub struct FixedTimestep {
+	pub update: ast::Ident,
+	pub render: ast::Ident,
+	pub span  : codemap::Span,
+}
+
+impl FixedTimestep {
+	fn parse(parser: &mut Parser) -> FixedTimestep {
+		let span = parser.span;
+
+		let update = parser.parse_ident();
+		parser.expect(&token::Comma);
+		let render = parser.parse_ident();
+		parser.expect(&token::Semi);
+
+		FixedTimestep {
+			update: update,
+			render: render,
+			span  : span,
+		}
+	}
+}
+
+
+#[deriving(Clone, PartialEq, Show)]
+pub enum HookKind {
+	Add,
+	Remove,
+}
+
+/// A function that is called whenever a component is added to or removed from
+/// an entity.
+#[deriving(Show)]
+pub struct Hook {
+	pub kind     : HookKind,
+	pub component: ast::Ident,
+	pub function : ast::Ident,
+	pub span     : codemap::Span,
+}
+
+impl Hook {
+	fn parse(parser: &mut Parser, kind: HookKind) -> Hook {
+		let span = parser.span;
+
+		parser.expect(&token::OpenDelim(token::Paren));
+		let component = parser.parse_ident();
+		parser.expect(&token::CloseDelim(token::Paren));
+		parser.expect(&token::Eq);
+		let function = parser.parse_ident();
+		parser.expect(&token::Semi);
+
+		Hook {
+			kind     : kind,
+			component: component,
+			function : function,
+			span     : span,
+		}
+	}
+}
+
+
+#[deriving(Show)]
+pub struct Prefab {
+	pub name      : ast::Ident,
+	pub components: Vec<(ast::Ident, P<ast::Expr>)>,
+	pub span      : codemap::Span,
+}
+
+impl Prefab {
+	fn parse(parser: &mut Parser) -> Prefab {
+		let span = parser.span;
+		let name = parser.parse_ident();
+		parser.expect(&token::Eq);
+
+		let mut components = Vec::new();
+		loop {
+			let component = parser.parse_ident();
+			parser.expect(&token::OpenDelim(token::Paren));
+			let value = parser.parse_expr();
+			parser.expect(&token::CloseDelim(token::Paren));
+
+			components.push((component, value));
+
+			parser.eat(&token::Comma);
+			if parser.eat(&token::Semi) {
+				break;
+			}
+		}
+
+		Prefab {
+			name      : name,
 			components: components,
+			span      : span,
 		}
 	}
 }